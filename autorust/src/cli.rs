@@ -1,13 +1,25 @@
-use autorust_codegen::Config;
+use autorust_codegen::{Config, ModelAttributes, RefKey, Target, TypeFormat};
 use clap::{App, Arg, ArgMatches};
 use snafu::{OptionExt, Snafu};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 #[derive(Debug, Snafu)]
 pub enum Error {
     InputFileIsRequired,
     OutputFolder,
+    #[snafu(display("unknown target {}", target))]
+    UnknownTarget {
+        target: String,
+    },
+    #[snafu(display("schema type {} is not FILE#NAME=TYPE", value))]
+    InvalidSchemaType {
+        value: String,
+    },
+    #[snafu(display("format type {} is not TYPE:FORMAT=TYPE", value))]
+    InvalidFormatType {
+        value: String,
+    },
 }
 
 const NAME: &str = env!("CARGO_PKG_NAME");
@@ -16,6 +28,17 @@ const INPUT_FILE: &str = "input-file";
 const OUTPUT_FOLDER: &str = "output-folder";
 const GENERATED: &str = "generated";
 const API_VERSION: &str = "api-version";
+const INPUT_MODELS: &str = "input-models";
+const VALIDATION: &str = "validation";
+const RESOURCE_IDS: &str = "resource-ids";
+const UNFORMATTED: &str = "unformatted";
+const SPLIT_FILES: &str = "split-files";
+const PRUNE_MODELS: &str = "prune-models";
+const TARGET: &str = "target";
+const BLOCKING: &str = "blocking";
+const TRACING: &str = "tracing";
+const SCHEMA_TYPE: &str = "schema-type";
+const FORMAT_TYPE: &str = "format-type";
 
 pub fn config_try_new() -> Result<Config> {
     let arg_matches = new_app().get_matches();
@@ -32,14 +55,81 @@ fn config_try_new_from_matches(arg_matches: &ArgMatches) -> Result<Config> {
     let output_folder = arg_matches.value_of(OUTPUT_FOLDER).context(OutputFolder)?.to_owned().into();
     let api_version = arg_matches.value_of(API_VERSION).map(String::from);
     let box_properties = HashSet::new();
+    let schema_types = arg_matches
+        .values_of(SCHEMA_TYPE)
+        .into_iter()
+        .flatten()
+        .map(parse_schema_type)
+        .collect::<Result<_>>()?;
+    let format_types = arg_matches
+        .values_of(FORMAT_TYPE)
+        .into_iter()
+        .flatten()
+        .map(parse_format_type)
+        .collect::<Result<_>>()?;
+    let model_attributes = ModelAttributes::default();
+    let schema_model_attributes = HashMap::new();
+    let unformatted = arg_matches.is_present(UNFORMATTED);
+    let split_files = arg_matches.is_present(SPLIT_FILES);
+    let feature_name = None;
+    let prune_models = arg_matches.is_present(PRUNE_MODELS);
+    let target = arg_matches.value_of(TARGET).unwrap_or_default();
+    let target = Target::from_name(target).context(UnknownTarget { target })?;
+    let blocking = arg_matches.is_present(BLOCKING);
+    let tracing = arg_matches.is_present(TRACING);
+    let input_models = arg_matches.is_present(INPUT_MODELS);
+    let validation = arg_matches.is_present(VALIDATION);
+    let resource_ids = arg_matches.is_present(RESOURCE_IDS);
     Ok(Config {
         input_files,
         output_folder,
         api_version,
         box_properties,
+        input_models,
+        validation,
+        resource_ids,
+        schema_types,
+        format_types,
+        model_attributes,
+        schema_model_attributes,
+        unformatted,
+        split_files,
+        feature_name,
+        prune_models,
+        target,
+        blocking,
+        tracing,
     })
 }
 
+/// Parses a `FILE#NAME=TYPE`, such as `common.json#ErrorResponse=azure_core::ErrorResponse`.
+fn parse_schema_type(value: &str) -> Result<(RefKey, String)> {
+    let (ref_key, tp) = value.rsplit_once('=').context(InvalidSchemaType { value })?;
+    let (file_path, name) = ref_key.rsplit_once('#').context(InvalidSchemaType { value })?;
+    if file_path.is_empty() || name.is_empty() || tp.is_empty() {
+        return InvalidSchemaType { value }.fail();
+    }
+    let ref_key = RefKey {
+        file_path: file_path.into(),
+        name: name.to_owned(),
+    };
+    Ok((ref_key, tp.to_owned()))
+}
+
+/// Parses a `TYPE:FORMAT=TYPE`, such as `string:uuid=uuid::Uuid`.
+fn parse_format_type(value: &str) -> Result<(TypeFormat, String)> {
+    let (type_format, tp) = value.rsplit_once('=').context(InvalidFormatType { value })?;
+    let (type_, format) = type_format.split_once(':').context(InvalidFormatType { value })?;
+    if type_.is_empty() || format.is_empty() || tp.is_empty() {
+        return InvalidFormatType { value }.fail();
+    }
+    let type_format = TypeFormat {
+        type_: type_.to_owned(),
+        format: format.to_owned(),
+    };
+    Ok((type_format, tp.to_owned()))
+}
+
 fn new_app() -> App<'static> {
     App::new(NAME)
         .version(VERSION)
@@ -64,6 +154,68 @@ fn new_app() -> App<'static> {
                 .long(API_VERSION)
                 .takes_value(true),
         )
+        .arg(
+            Arg::new(INPUT_MODELS)
+                .about("also create request models that leave out read-only properties, for the request bodies")
+                .long(INPUT_MODELS),
+        )
+        .arg(
+            Arg::new(VALIDATION)
+                .about("check schema constraints on models and parameters before sending requests")
+                .long(VALIDATION),
+        )
+        .arg(
+            Arg::new(RESOURCE_IDS)
+                .about("create typed ARM resource IDs and functions that take them")
+                .long(RESOURCE_IDS),
+        )
+        .arg(
+            Arg::new(UNFORMATTED)
+                .about("write the generated code on a single line instead of pretty-printing it")
+                .long(UNFORMATTED),
+        )
+        .arg(
+            Arg::new(SPLIT_FILES)
+                .about("write a file for the models of each input file and for each operation group")
+                .long(SPLIT_FILES),
+        )
+        .arg(
+            Arg::new(PRUNE_MODELS)
+                .about("only create the models that are reachable from the operations")
+                .long(PRUNE_MODELS),
+        )
+        .arg(
+            Arg::new(TARGET)
+                .about("the runtime that the config function in mod.rs is created for")
+                .long(TARGET)
+                .takes_value(true)
+                .possible_values(Target::NAMES)
+                .default_value(Target::default().name()),
+        )
+        .arg(
+            Arg::new(BLOCKING)
+                .about("also create blocking variants of the operations")
+                .long(BLOCKING),
+        )
+        .arg(
+            Arg::new(TRACING)
+                .about("trace the operations in spans when the tracing feature of the runtime is enabled")
+                .long(TRACING),
+        )
+        .arg(
+            Arg::new(SCHEMA_TYPE)
+                .about("a type to use instead of creating a model for a schema definition, as FILE#NAME=TYPE")
+                .long(SCHEMA_TYPE)
+                .takes_value(true)
+                .multiple(true),
+        )
+        .arg(
+            Arg::new(FORMAT_TYPE)
+                .about("a type to use for the schemas with a type and format, as TYPE:FORMAT=TYPE")
+                .long(FORMAT_TYPE)
+                .takes_value(true)
+                .multiple(true),
+        )
 }
 
 #[cfg(test)]
@@ -112,6 +264,53 @@ mod tests {
         assert_eq!(c.input_files, input_files);
         let output_folder: PathBuf = "src".into();
         assert_eq!(c.output_folder, output_folder);
+        assert!(!c.input_models);
+        Ok(())
+    }
+
+    #[test]
+    fn input_models() -> Result<()> {
+        let m = new_app().try_get_matches_from(vec![NAME, "--input-file=abc.json", "--input-models"])?;
+        let c = config_try_new_from_matches(&m)?;
+        assert!(c.input_models);
+        Ok(())
+    }
+
+    #[test]
+    fn target() -> Result<()> {
+        let m = new_app().try_get_matches_from(vec![NAME, "--input-file=abc.json"])?;
+        assert_eq!(config_try_new_from_matches(&m)?.target, Target::AzureCore);
+        let m = new_app().try_get_matches_from(vec![NAME, "--input-file=abc.json", "--target=standalone"])?;
+        assert_eq!(config_try_new_from_matches(&m)?.target, Target::Standalone);
+        assert!(new_app()
+            .try_get_matches_from(vec![NAME, "--input-file=abc.json", "--target=reqwest"])
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn schema_and_format_types() -> Result<()> {
+        let m = new_app().try_get_matches_from(vec![
+            NAME,
+            "--input-file=abc.json",
+            "--schema-type=common.json#ErrorResponse=azure_core::ErrorResponse",
+            "--format-type=string:uuid=uuid::Uuid",
+            "--format-type=string:date-time=chrono::DateTime<chrono::Utc>",
+        ])?;
+        let c = config_try_new_from_matches(&m)?;
+        let ref_key = RefKey {
+            file_path: "common.json".into(),
+            name: "ErrorResponse".to_owned(),
+        };
+        assert_eq!(c.schema_types[&ref_key], "azure_core::ErrorResponse");
+        let type_format = TypeFormat {
+            type_: "string".to_owned(),
+            format: "uuid".to_owned(),
+        };
+        assert_eq!(c.format_types[&type_format], "uuid::Uuid");
+        assert_eq!(c.format_types.len(), 2);
+        let m = new_app().try_get_matches_from(vec![NAME, "--input-file=abc.json", "--schema-type=ErrorResponse=x::Y"])?;
+        assert!(config_try_new_from_matches(&m).is_err());
         Ok(())
     }
 }
@@ -1,6 +1,6 @@
 mod cli;
 
-use autorust_codegen::run;
+use autorust_codegen::generate;
 use cli::config_try_new;
 
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -8,6 +8,29 @@ pub type Result<T> = std::result::Result<T, Error>;
 
 fn main() -> Result<()> {
     let config = config_try_new()?;
-    run(config)?;
+    let generation = generate(&config)?;
+    for model in generation.pruned_models() {
+        println!("pruned {}", model);
+    }
+    for model in generation.renamed_models() {
+        println!("renamed {}", model);
+    }
+    for property in generation.unflattened_properties() {
+        println!("not flattened {}", property);
+    }
+    for function in generation.renamed_functions() {
+        println!("renamed function {}", function);
+    }
+    let report = generation.write()?;
+    for file in &report.added {
+        println!("added {}", file.display());
+    }
+    for file in &report.changed {
+        println!("changed {}", file.display());
+    }
+    for file in &report.removed {
+        println!("removed {}", file.display());
+    }
+    println!("{}", report);
     Ok(())
 }
@@ -3,14 +3,27 @@
 use autorust_codegen::{
     self, cargo_toml,
     config_parser::{to_api_version, to_mod_name},
-    get_mgmt_configs, lib_rs, path, Config, PropertyName, SpecConfigs,
+    get_mgmt_configs, lib_rs, manifest, path, Config, Generation, PropertyName, SpecConfigs, Target,
 };
 use heck::SnakeCase;
 use snafu::{ResultExt, Snafu};
-use std::{collections::HashSet, fs, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
 const OUTPUT_FOLDER: &str = "../azure-sdk-for-rust/services/mgmt";
 
+const TARGET: Target = Target::AzureCore;
+
+/// The path of the runtime from a generated crate, with this repository checked out next to `azure-sdk-for-rust`
+const RUNTIME_PATH: &str = "../../../../autorust/runtime";
+
+const TRACING: bool = true;
+
+/// The blocking operations need a synchronous HTTP client, which the `azure_core` target does not take
+const BLOCKING: bool = false;
+
 const ONLY_SERVICES: &[&str] = &[
     // "vmware",
     // "network",
@@ -18,15 +31,10 @@ const ONLY_SERVICES: &[&str] = &[
 ];
 
 const SKIP_SERVICES: &[&str] = &[
-    "automation",                 // TODO #81 DataType::File
-    "deploymentmanager",          // TODO #80 path parameters
-    "deviceprovisioningservices", // TODO #82 certificate_name used as parameter more than once
-    "dnc",                        // https://github.com/Azure/azure-rest-api-specs/pull/11578 two ControllerDetails types
-    "mixedreality",               // TODO #83 AccountKeyRegenerateRequest not generated
-    "netapp",                     // Ident "10minutely"
-    "powerplatform",              // https://github.com/Azure/azure-rest-api-specs/pull/11580 incorrect ref & duplicate Operations_List
-    "service-map",                // Ident "Ref:machine"
-    "servicefabric",              // https://github.com/Azure/azure-rest-api-specs/pull/11581 allOf mistakes and duplicate Operations_List
+    "automation",        // TODO #81 DataType::File
+    "deploymentmanager", // TODO #80 path parameters
+    "powerplatform",     // https://github.com/Azure/azure-rest-api-specs/pull/11580 incorrect ref & duplicate Operations_List
+    "servicefabric",     // https://github.com/Azure/azure-rest-api-specs/pull/11581 allOf mistakes and duplicate Operations_List
     "servicefabricmanagedclusters",
     "web", // TODO #81 DataType::File
 ];
@@ -35,12 +43,9 @@ const SKIP_SERVICE_TAGS: &[(&str, &str)] = &[
     ("azureactivedirectory", "package-preview-2020-07"),
     ("resources", "package-policy-2020-03"),
     ("resources", "package-policy-2020-09"), // SchemaNotFound { ref_key: RefKey { file_path: "../azure-rest-api-specs/specification/resources/resource-manager/Microsoft.Authorization/stable/2020-09-01/dataPolicyManifests.json", name: "CloudError"
-    ("recoveryservicesbackup", "package-2020-07"), // duplicate fn get_operation_status
-    ("recoveryservicesbackup", "package-2020-10"), // duplicate fn get_operation_status
     ("network", "package-2017-03-30-only"),  // SchemaNotFound 2017-09-01/network.json SubResource
     ("synapse", "package-2019-06-01-preview"), // TODO #80 path parameters
     ("recoveryservicessiterecovery", "package-2016-08"), // duplicate package-2016-08 https://github.com/Azure/azure-rest-api-specs/pull/11287
-    ("mediaservices", "package-2019-05-preview"), // invalid unicode character of a dash instead of a hyphen https://github.com/Azure/azure-rest-api-specs/pull/11576
     // datamigration, same error for all
     // SchemaNotFound MigrateSqlServerSqlDbTask.json ValidationStatus, but may be buried
     ("datamigration", "package-2018-07-15-preview"),
@@ -82,24 +87,21 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 pub enum Error {
     #[snafu(display("file name was not utf-8"))]
     FileNameNotUtf8Error {},
-    IoError {
-        source: std::io::Error,
-    },
     PathError {
         source: path::Error,
     },
     CodegenError {
         source: autorust_codegen::Error,
     },
-    CargoTomlError {
-        source: cargo_toml::Error,
-    },
     LibRsError {
         source: lib_rs::Error,
     },
     GetSpecFoldersError {
         source: autorust_codegen::Error,
     },
+    ManifestError {
+        source: manifest::Error,
+    },
 }
 
 fn main() -> Result<()> {
@@ -125,11 +127,10 @@ fn gen_crate(spec: &SpecConfigs) -> Result<()> {
     let output_folder = &path::join(OUTPUT_FOLDER, service_name).context(PathError)?;
 
     let src_folder = path::join(output_folder, "src").context(PathError)?;
-    if src_folder.exists() {
-        fs::remove_dir_all(&src_folder).context(IoError)?;
-    }
-
+    // only the files that have changed are written, and only the files that were generated before are removed
+    let mut generation = Generation::new(output_folder);
     let mut feature_mod_names = Vec::new();
+    let mut group_features = HashMap::new();
     let skip_service_tags: HashSet<&(&str, &str)> = SKIP_SERVICE_TAGS.iter().collect();
 
     let mut box_properties = HashSet::new();
@@ -167,25 +168,51 @@ fn gen_crate(spec: &SpecConfigs) -> Result<()> {
             // for input_file in &input_files {
             //     println!("  {:?}", input_file);
             // }
-            autorust_codegen::run(Config {
+            let mod_generation = autorust_codegen::generate(&Config {
                 api_version: Some(api_version),
-                output_folder: mod_output_folder.into(),
+                output_folder: mod_output_folder,
                 input_files,
                 box_properties: box_properties.clone(),
+                feature_name: Some(tag.to_string()),
+                target: TARGET,
+                tracing: TRACING,
+                blocking: BLOCKING,
+                ..Default::default()
             })
             .context(CodegenError)?;
+            group_features.insert(tag.to_string(), mod_generation.features().to_vec());
+            generation.extend(Path::new("src").join(mod_name), mod_generation);
         }
     }
     if feature_mod_names.len() == 0 {
         return Ok(());
     }
-    cargo_toml::create(
+    let cargo_toml = cargo_toml::create_content(
         crate_name,
         &feature_mod_names,
-        &path::join(output_folder, "Cargo.toml").context(PathError)?,
-    )
-    .context(CargoTomlError)?;
-    lib_rs::create(&feature_mod_names, &path::join(src_folder, "lib.rs").context(PathError)?).context(LibRsError)?;
+        &group_features,
+        generation.dependencies(),
+        TARGET,
+        RUNTIME_PATH,
+        TRACING,
+        BLOCKING,
+    );
+    generation.add_file("Cargo.toml", cargo_toml);
+    generation.add_file(
+        "src/lib.rs",
+        lib_rs::create_code(&feature_mod_names, &group_features).context(LibRsError)?,
+    );
+    for model in generation.renamed_models() {
+        println!("  renamed {}", model);
+    }
+    for property in generation.unflattened_properties() {
+        println!("  not flattened {}", property);
+    }
+    for function in generation.renamed_functions() {
+        println!("  renamed function {}", function);
+    }
+    let report = generation.write().context(ManifestError)?;
+    println!("  {}", report);
 
     Ok(())
 }
@@ -19,6 +19,7 @@ fn main() -> Result<()> {
         output_folder: output_folder.into(),
         input_files: input_files.iter().map(Into::into).collect(),
         box_properties: HashSet::new(),
+        ..Default::default()
     })?;
 
     let api_version = "2019-06-01";
@@ -35,6 +36,7 @@ fn main() -> Result<()> {
         output_folder: output_folder.into(),
         input_files: input_files.iter().map(Into::into).collect(),
         box_properties: HashSet::new(),
+        ..Default::default()
     })?;
 
     Ok(())
@@ -1,5 +1,7 @@
+use crate::Target;
 use snafu::{ResultExt, Snafu};
 use std::{
+    collections::HashMap,
     fs::File,
     io::{prelude::*, LineWriter},
     path::Path,
@@ -11,48 +13,111 @@ pub enum Error {
     IoError { source: std::io::Error },
 }
 
-pub fn create(crate_name: &str, feature_mod_names: &Vec<(String, String)>, path: &Path) -> Result<()> {
+pub fn create(
+    crate_name: &str,
+    feature_mod_names: &Vec<(String, String)>,
+    target: Target,
+    runtime_path: &str,
+    tracing: bool,
+    blocking: bool,
+    path: &Path,
+) -> Result<()> {
     let file = File::create(path).context(IoError)?;
     let mut file = LineWriter::new(file);
-    let version = &env!("CARGO_PKG_VERSION");
     file.write_all(
-        format!(
-            r#"# generated by AutoRust {}
+        create_content(
+            crate_name,
+            feature_mod_names,
+            &HashMap::new(),
+            &[],
+            target,
+            runtime_path,
+            tracing,
+            blocking,
+        )
+        .as_bytes(),
+    )
+    .context(IoError)?;
+    Ok(())
+}
+
+/// Creates the content of `Cargo.toml`, without writing it.
+/// The features of the operation groups of a module, by its feature, are enabled by the feature of the module.
+/// The optional dependencies are only those that the generated code uses, as recorded in its `Generation`.
+/// The dependencies on the runtime are those of the target, with the runtime at `runtime_path` from the generated crate.
+/// When the operations are traced, the `tracing` feature enables the tracing of the runtime.
+/// The blocking operations need the synchronous HTTP client of the runtime.
+#[allow(clippy::too_many_arguments)]
+pub fn create_content(
+    crate_name: &str,
+    feature_mod_names: &Vec<(String, String)>,
+    group_features: &HashMap<String, Vec<String>>,
+    dependencies: &[String],
+    target: Target,
+    runtime_path: &str,
+    tracing: bool,
+    blocking: bool,
+) -> String {
+    let version = &env!("CARGO_PKG_VERSION");
+    let mut content = format!(
+        r#"# generated by AutoRust {}
 [package]
 name = "{}"
 version = "0.1.0"
 edition = "2018"
 
 [dependencies]
-azure_core = {{ path = "../../../sdk/core", version = "0.1.0" }}
-serde = {{ version = "1.0", features = ["derive"] }}
+{}serde = {{ version = "1.0", features = ["derive"] }}
 serde_json = "1.0"
-reqwest = {{ version = "0.11", features = ["json"] }}
 bytes = "1.0"
 snafu = "0.6"
 http = "0.2"
-url = "2.2"
-
+{}
 [dev-dependencies]
-azure_identity = {{ path = "../../../sdk/identity", version = "0.1.0" }}
-tokio = {{ version = "1.0", features = ["macros"] }}
+{}tokio = {{ version = "1.0", features = ["macros"] }}
 
 [features]
 "#,
-            version, crate_name
-        )
-        .as_bytes(),
-    )
-    .context(IoError)?;
-
+        version,
+        crate_name,
+        target.dependencies(runtime_path, blocking),
+        get_optional_dependencies(dependencies),
+        target.dev_dependencies()
+    );
     let default = get_default_feature(feature_mod_names);
-    file.write_all(format!("default = [\"{}\"]\n", default).as_bytes())
-        .context(IoError)?;
-
+    content.push_str(&format!("default = [\"{}\"]\n", default));
+    if tracing {
+        content.push_str("tracing = [\"autorust_runtime/tracing\"]\n");
+    }
     for (feature_name, _mod_name) in feature_mod_names {
-        file.write_all(format!("\"{}\" = []\n", feature_name).as_bytes()).context(IoError)?;
+        let groups = group_features.get(feature_name).map(Vec::as_slice).unwrap_or_default();
+        let groups: Vec<_> = groups.iter().map(|group| format!("\"{}\"", group)).collect();
+        content.push_str(&format!("\"{}\" = [{}]\n", feature_name, groups.join(", ")));
+        for group in groups {
+            content.push_str(&format!("{} = []\n", group));
+        }
     }
-    Ok(())
+    let patches = target.patches();
+    if !patches.is_empty() {
+        content.push_str("\n[patch.crates-io]\n");
+        content.push_str(&patches);
+    }
+    content
+}
+
+/// The optional dependencies, by their name, with their line of `Cargo.toml`
+const OPTIONAL_DEPENDENCIES: &[(&str, &str)] = &[
+    ("serde_repr", r#"serde_repr = "0.1""#),
+    ("lazy_static", r#"lazy_static = "1.4""#),
+    ("regex", r#"regex = "1.4""#),
+];
+
+fn get_optional_dependencies(dependencies: &[String]) -> String {
+    OPTIONAL_DEPENDENCIES
+        .iter()
+        .filter(|(name, _)| dependencies.iter().any(|dependency| dependency == name))
+        .map(|(_, line)| format!("{}\n", line))
+        .collect()
 }
 
 fn get_default_feature(feature_mod_names: &Vec<(String, String)>) -> String {
@@ -65,3 +130,62 @@ fn get_default_feature(feature_mod_names: &Vec<(String, String)>) -> String {
         None => feature_mod_names[0].0.clone(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_content_with_group_features() {
+        let feature_mod_names = vec![
+            ("package-2021".to_owned(), "package_2021".to_owned()),
+            ("package-2020".to_owned(), "package_2020".to_owned()),
+        ];
+        let mut group_features = HashMap::new();
+        group_features.insert(
+            "package-2021".to_owned(),
+            vec!["package-2021-widgets".to_owned(), "package-2021-gadgets".to_owned()],
+        );
+        let content = create_content(
+            "azure_mgmt_test",
+            &feature_mod_names,
+            &group_features,
+            &[],
+            Target::AzureCore,
+            "../runtime",
+            false,
+            false,
+        );
+        let features = content.split("[features]\n").nth(1).unwrap();
+        assert_eq!(
+            features.split("\n\n").next().unwrap(),
+            r#"default = ["package-2021"]
+"package-2021" = ["package-2021-widgets", "package-2021-gadgets"]
+"package-2021-widgets" = []
+"package-2021-gadgets" = []
+"package-2020" = []"#
+        );
+    }
+
+    #[test]
+    fn test_create_content_with_optional_dependencies() {
+        let feature_mod_names = vec![("package-2021".to_owned(), "package_2021".to_owned())];
+        let create = |dependencies: &[String]| {
+            create_content(
+                "azure_mgmt_test",
+                &feature_mod_names,
+                &HashMap::new(),
+                dependencies,
+                Target::AzureCore,
+                "../runtime",
+                false,
+                false,
+            )
+        };
+        let content = create(&[]);
+        assert!(!content.contains("regex"));
+        assert!(!content.contains("lazy_static"));
+        let content = create(&["regex".to_owned(), "lazy_static".to_owned()]);
+        assert!(content.contains("http = \"0.2\"\nlazy_static = \"1.4\"\nregex = \"1.4\"\n\n[dev-dependencies]"));
+    }
+}
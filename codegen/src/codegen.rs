@@ -1,22 +1,28 @@
 use crate::{
-    identifier::{ident, CamelCaseIdent},
+    doc_comment::create_doc,
+    identifier::{ident, CamelCaseIdent, UniqueIdents},
+    resource_id::{self, ResourceIdPattern},
     spec,
     status_codes::{get_error_responses, get_response_type_name, get_status_code_name, get_success_responses, has_default_response},
-    Config, OperationVerb, PropertyName, ResolvedSchema, Spec,
+    Config, ModelAttributes, OperationVerb, PropertyName, RenamedFunction, ResolvedSchema, Spec, Target, TypeFormat,
 };
 use autorust_openapi::{
-    CollectionFormat, DataType, Parameter, ParameterType, PathItem, Reference, ReferenceOr, Response, Schema, SchemaCommon,
+    CollectionFormat, DataType, MsMutability, Operation, Parameter, ParameterType, PathItem, Reference, ReferenceOr, Response, Schema,
+    SchemaCommon,
 };
 use heck::{CamelCase, SnakeCase};
-use indexmap::IndexMap;
-use proc_macro2::TokenStream;
-use quote::quote;
+use indexmap::{IndexMap, IndexSet};
+use proc_macro2::{Group, Literal, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
 use regex::Regex;
 use serde_json::Value;
 use snafu::{OptionExt, ResultExt, Snafu};
-use spec::{get_schema_schema_references, openapi, RefKey};
+use spec::{get_parameter_schema_references, get_schema_schema_references, openapi, Deprecation, RefKey};
 use std::{
-    collections::HashSet,
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    ffi::OsStr,
     path::{Path, PathBuf},
 };
 
@@ -24,12 +30,87 @@ use std::{
 pub struct CodeGen {
     config: Config,
     pub spec: Spec,
+    /// The names of the types for the schemas, where different schemas with the same name have been disambiguated
+    type_names: IndexMap<RefKey, String>,
+    /// The schemas whose types have been renamed, as they differ from another schema with the same name, and their type names
+    renamed_schemas: Vec<(RefKey, String)>,
+    /// The names of the input models for the schemas that have one, when creating input models
+    input_type_names: IndexMap<RefKey, String>,
+    /// The typed resource IDs for the resource paths, by the key of their pattern
+    resource_ids: IndexMap<String, (TokenStream, ResourceIdPattern)>,
+    /// The configured types to use for schema definitions, instead of creating models
+    schema_types: HashMap<RefKey, TokenStream>,
+    /// The configured types to use for schemas with a `type` and `format`
+    format_types: HashMap<TypeFormat, TokenStream>,
+    /// The configured derives and attributes for all the models
+    model_attributes: ModelAttributesCode,
+    /// The configured derives and attributes for the models of schema definitions, by the name of the definition
+    schema_model_attributes: HashMap<String, ModelAttributesCode>,
+    /// The schemas that the operations of each operation group use, when the operation groups are features or the models are pruned
+    group_schemas: IndexMap<Option<String>, IndexSet<RefKey>>,
+    /// The schemas that are reachable from the operations, when the models are pruned
+    reachable_schemas: Option<IndexSet<RefKey>>,
+    /// Whether an integer enum has been created, which derives the traits of `serde_repr`
+    has_integer_enums: Cell<bool>,
+    /// The `x-ms-client-flatten` properties that have been kept nested, as their fields would have the names of other fields
+    unflattened_properties: RefCell<IndexSet<PropertyName>>,
+    /// The operations whose functions have been renamed, as they have the names of other functions
+    renamed_functions: RefCell<Vec<RenamedFunction>>,
 }
 
 impl CodeGen {
     pub fn new(config: Config) -> Result<Self> {
+        if config.blocking && !config.target.supports_blocking() {
+            return BlockingTargetError {
+                target: config.target.name(),
+            }
+            .fail();
+        }
         let spec = Spec::read_files(&config.input_files).context(SpecError)?;
-        Ok(Self { config, spec })
+        let mut cg = Self {
+            config,
+            spec,
+            type_names: IndexMap::new(),
+            renamed_schemas: Vec::new(),
+            input_type_names: IndexMap::new(),
+            resource_ids: IndexMap::new(),
+            schema_types: HashMap::new(),
+            format_types: HashMap::new(),
+            model_attributes: ModelAttributesCode::default(),
+            schema_model_attributes: HashMap::new(),
+            group_schemas: IndexMap::new(),
+            reachable_schemas: None,
+            has_integer_enums: Cell::new(false),
+            unflattened_properties: RefCell::new(IndexSet::new()),
+            renamed_functions: RefCell::new(Vec::new()),
+        };
+        for (ref_key, type_path) in &cg.config.schema_types {
+            cg.schema_types.insert(ref_key.clone(), parse_type_path(type_path)?);
+        }
+        for (type_format, type_path) in &cg.config.format_types {
+            cg.format_types.insert(type_format.clone(), parse_type_path(type_path)?);
+        }
+        cg.model_attributes = parse_model_attributes(&cg.config.model_attributes)?;
+        for (schema_name, model_attributes) in &cg.config.schema_model_attributes {
+            cg.schema_model_attributes
+                .insert(schema_name.clone(), parse_model_attributes(model_attributes)?);
+        }
+        if cg.config.feature_name.is_some() || cg.config.prune_models {
+            cg.group_schemas = cg.get_operation_group_schemas()?;
+        }
+        if cg.config.prune_models {
+            cg.reachable_schemas = Some(cg.get_reachable_schemas(&cg.all_definitions()?)?);
+        }
+        let (type_names, renamed_schemas) = cg.create_type_names()?;
+        cg.type_names = type_names;
+        cg.renamed_schemas = renamed_schemas;
+        if cg.config.input_models {
+            cg.input_type_names = cg.create_input_type_names()?;
+        }
+        if cg.config.resource_ids {
+            cg.resource_ids = cg.create_resource_ids()?;
+        }
+        Ok(cg)
     }
 
     pub fn input_files(&self) -> &[PathBuf] {
@@ -44,14 +125,492 @@ impl CodeGen {
         self.config.api_version.as_deref()
     }
 
-    pub fn create_models(&self) -> Result<TokenStream> {
-        let mut file = TokenStream::new();
-        file.extend(create_generated_by_header());
-        file.extend(quote! {
-            #![allow(non_camel_case_types)]
-            #![allow(unused_imports)]
-            use serde::{Deserialize, Serialize};
-        });
+    /// Whether the models that have been created include an integer enum, so the crate depends on `serde_repr`.
+    pub fn has_integer_enums(&self) -> bool {
+        self.has_integer_enums.get()
+    }
+
+    /// Gets the `x-ms-client-flatten` properties of the models that have been created that are kept nested,
+    /// as their fields would have the names of other fields.
+    pub fn unflattened_properties(&self) -> Vec<PropertyName> {
+        self.unflattened_properties.borrow().iter().cloned().collect()
+    }
+
+    /// Gets the operations whose functions have been renamed by `create_operations`,
+    /// as another operation in their operation group has a function with the same name.
+    pub fn renamed_functions(&self) -> Vec<RenamedFunction> {
+        self.renamed_functions.borrow().clone()
+    }
+
+    fn create_enum(
+        &self,
+        namespace: &TokenStream,
+        property_name: &str,
+        property: &ResolvedSchema,
+        deprecation: Option<&Deprecation>,
+        model: &ModelAttributesCode,
+    ) -> Result<(TokenStream, TokenStream)> {
+        if let EnumType::Integer = get_enum_type(&property.schema) {
+            self.has_integer_enums.set(true);
+        }
+        create_enum(namespace, property_name, property, deprecation, model)
+    }
+
+    /// Gets the name of the type created for a schema definition, preferring its `x-ms-client-name`.
+    fn type_name<'a>(&'a self, ref_key: &'a RefKey) -> &'a str {
+        match (self.type_names.get(ref_key), self.spec.schema(ref_key)) {
+            (Some(name), _) => name,
+            (None, Some(schema)) => get_client_name(&ref_key.name, &schema.common),
+            (None, None) => &ref_key.name,
+        }
+    }
+
+    fn get_type_name(&self, ref_key: &RefKey) -> Result<TokenStream> {
+        if let Some(type_path) = self.schema_types.get(ref_key) {
+            return Ok(type_path.clone());
+        }
+        ident(&self.type_name(ref_key).to_camel_case()).context(IdentError {
+            file: file!(),
+            line: line!(),
+        })
+    }
+
+    /// Gets the name of the input model of a schema definition, if it has one.
+    fn get_input_type_name(&self, ref_key: &RefKey) -> Result<Option<TokenStream>> {
+        match self.input_type_names.get(ref_key) {
+            Some(name) => Ok(Some(ident(name).context(IdentError {
+                file: file!(),
+                line: line!(),
+            })?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether a schema definition is mapped to a configured type, so no model is created for it.
+    fn is_mapped(&self, ref_key: &RefKey) -> bool {
+        self.schema_types.contains_key(ref_key)
+    }
+
+    /// Gets the configured type for the `type` and `format` of a schema.
+    fn get_format_type(&self, schema: &SchemaCommon) -> Option<&TokenStream> {
+        let type_ = get_data_type_name(schema.type_.as_ref()?);
+        let format = schema.format.as_ref()?;
+        self.format_types.get(&TypeFormat {
+            type_: type_.to_owned(),
+            format: format.to_owned(),
+        })
+    }
+
+    /// Whether a derive is configured for the model of a schema definition, or for all the models.
+    fn has_configured_derive(&self, schema_name: Option<&str>, derive: &str) -> bool {
+        let schema_model_attributes = schema_name.and_then(|name| self.schema_model_attributes.get(name));
+        std::iter::once(&self.model_attributes)
+            .chain(schema_model_attributes)
+            .any(|model_attributes| model_attributes.derives.iter().any(|(name, _)| name == derive))
+    }
+
+    /// Gets the configured derives and attributes for the model of a schema definition, or for a local type when there is no name.
+    /// The derives that the model always has, and those that the types of its fields do not allow, are left out.
+    /// `Default` is left out too, as it is implemented along with the constructor.
+    fn get_model_attributes(&self, schema_name: Option<&str>, traits: TypeTraits) -> ModelAttributesCode {
+        let schema_model_attributes = schema_name.and_then(|name| self.schema_model_attributes.get(name));
+        let mut model = ModelAttributesCode::default();
+        for model_attributes in std::iter::once(&self.model_attributes).chain(schema_model_attributes) {
+            for (name, derive) in &model_attributes.derives {
+                let allowed = match name.as_str() {
+                    "Clone" | "Debug" | "PartialEq" | "Serialize" | "Deserialize" | "Default" => false,
+                    "Eq" => traits.eq,
+                    "Ord" => traits.eq && traits.ord,
+                    "PartialOrd" => traits.ord,
+                    "Hash" => traits.hash,
+                    _ => true,
+                };
+                if allowed && !model.derives.iter().any(|(other, _)| other == name) {
+                    model.derives.push((name.clone(), derive.clone()));
+                }
+            }
+            model.attributes.extend(model_attributes.attributes.iter().cloned());
+        }
+        model
+    }
+
+    /// Gets the traits that the type of a schema allows to be derived, checking the types of all of its fields.
+    /// Configured types are expected to implement the configured derives.
+    fn get_type_traits(&self, doc_file: &Path, schema: &ReferenceOr<Schema>, visited: &mut HashSet<RefKey>) -> Result<TypeTraits> {
+        match schema {
+            ReferenceOr::Reference { reference, .. } => {
+                let ref_key = self.spec.schema_ref_key(doc_file, reference).context(SpecError)?;
+                // a recursive type is checked where it is first visited
+                if self.is_mapped(&ref_key) || !visited.insert(ref_key.clone()) {
+                    return Ok(TypeTraits::ALL);
+                }
+                let resolved = self.spec.resolve_schema(doc_file, schema).context(SpecError)?;
+                self.get_schema_traits(&ref_key.file_path, Some(&ref_key.name), &resolved.schema, visited)
+            }
+            ReferenceOr::Item(schema) => self.get_schema_traits(doc_file, None, schema, visited),
+        }
+    }
+
+    /// Gets the traits that the type of a schema allows to be derived, where `schema_name` is the name of its definition.
+    fn get_schema_traits(
+        &self,
+        doc_file: &Path,
+        schema_name: Option<&str>,
+        schema: &Schema,
+        visited: &mut HashSet<RefKey>,
+    ) -> Result<TypeTraits> {
+        if self.get_format_type(&schema.common).is_some() {
+            return Ok(TypeTraits::ALL);
+        }
+        if schema.common.enum_.len() > 0 {
+            if let EnumType::String | EnumType::Integer = get_enum_type(schema) {
+                // only a constant has a default
+                return Ok(TypeTraits {
+                    default: schema.common.enum_.len() == 1,
+                    ..TypeTraits::ALL
+                });
+            }
+        }
+        match schema.common.type_ {
+            Some(DataType::Number) => Ok(TypeTraits {
+                eq: false,
+                ord: false,
+                hash: false,
+                default: true,
+            }),
+            Some(DataType::String) if self.config.resource_ids && is_arm_id(&schema.common) => Ok(TypeTraits {
+                default: false,
+                ..TypeTraits::ALL
+            }),
+            Some(DataType::Array) => {
+                let items = self.get_type_traits(doc_file, get_schema_array_items(&schema.common)?, visited)?;
+                Ok(TypeTraits { default: true, ..items })
+            }
+            Some(DataType::Object) | None if schema.properties.len() == 0 && schema.all_of.len() == 0 => Ok(TypeTraits {
+                ord: false,
+                hash: false,
+                ..TypeTraits::ALL
+            }),
+            Some(DataType::Object) | None => {
+                let mut traits = TypeTraits::ALL;
+                // the schemas in `allOf` are flattened into fields that are constructor parameters
+                let mut has_parameters = schema.all_of.len() > 0;
+                for all_of in &schema.all_of {
+                    traits = traits.and(self.get_type_traits(doc_file, all_of, visited)?);
+                }
+                for (property_name, property) in &schema.properties {
+                    let property_traits = self.get_type_traits(doc_file, property, visited)?;
+                    traits.eq &= property_traits.eq;
+                    traits.ord &= property_traits.ord;
+                    traits.hash &= property_traits.hash;
+                    // the fields without an initializer are constructor parameters, as they are in `create_struct_fields`
+                    let resolved = self.spec.resolve_schema(doc_file, property).context(SpecError)?;
+                    let field_tp_name = self.get_field_type_name(doc_file, &resolved)?;
+                    let is_required = schema.required.contains(property_name);
+                    let struct_name = schema_name.unwrap_or_default();
+                    if self
+                        .create_field_initializer(doc_file, struct_name, property_name, &resolved, &field_tp_name, is_required)?
+                        .is_none()
+                    {
+                        has_parameters = true;
+                        traits.default &= property_traits.default;
+                    }
+                }
+                traits.default = !has_parameters || (traits.default && self.has_configured_derive(schema_name, "Default"));
+                Ok(traits)
+            }
+            _ => Ok(TypeTraits::ALL),
+        }
+    }
+
+    /// Finds the resource paths of the operations and names their typed resource IDs.
+    fn create_resource_ids(&self) -> Result<IndexMap<String, (TokenStream, ResourceIdPattern)>> {
+        let mut names = UniqueIdents::default();
+        for name in &["ResourceId", "ParseResourceIdError"] {
+            names
+                .insert(ident(name).context(IdentError {
+                    file: file!(),
+                    line: line!(),
+                })?)
+                .context(IdentError {
+                    file: file!(),
+                    line: line!(),
+                })?;
+        }
+        let mut resource_ids = IndexMap::new();
+        for (doc_file, doc) in self.spec.docs() {
+            if self.spec.is_input_file(&doc_file) {
+                for path in doc.paths.keys() {
+                    if let Some(pattern) = ResourceIdPattern::parse(path) {
+                        if !resource_ids.contains_key(&pattern.key()) {
+                            let type_name = ident(&pattern.type_name()).context(IdentError {
+                                file: file!(),
+                                line: line!(),
+                            })?;
+                            let type_name = names.insert(type_name).context(IdentError {
+                                file: file!(),
+                                line: line!(),
+                            })?;
+                            resource_ids.insert(pattern.key(), (type_name, pattern));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(resource_ids)
+    }
+
+    /// Gets the typed resource ID of the longest resource path that an operation path starts with.
+    fn get_resource_id_for_path(&self, path: &str) -> Option<&(TokenStream, ResourceIdPattern)> {
+        self.resource_ids
+            .values()
+            .filter(|(_, pattern)| pattern.is_prefix_of(path))
+            .max_by_key(|(_, pattern)| pattern.parameters().len())
+    }
+
+    /// Gets the type for an ARM resource ID field.
+    /// It is the typed resource ID when `x-ms-arm-id-details` allows a single type of resource with a known path.
+    fn get_resource_id_type(&self, schema: &SchemaCommon) -> TokenStream {
+        let allowed_types: Vec<&str> = schema
+            .x_ms_arm_id_details
+            .iter()
+            .flat_map(|details| details.allowed_resources.iter().map(|resource| resource.type_.as_str()))
+            .collect();
+        if let [allowed_type] = allowed_types.as_slice() {
+            let matches: Vec<_> = self
+                .resource_ids
+                .values()
+                .filter(|(_, pattern)| {
+                    pattern
+                        .resource_type()
+                        .map_or(false, |resource_type| resource_type.eq_ignore_ascii_case(allowed_type))
+                })
+                .collect();
+            if let [(type_name, _)] = matches.as_slice() {
+                return quote! { resource_ids::#type_name };
+            }
+        }
+        quote! { resource_ids::ResourceId }
+    }
+
+    /// Names the types for all the schemas that models are created for.
+    /// Identical schemas with the same name share a type. When the schemas differ,
+    /// the later ones are renamed by prefixing the name of their file, and are returned along with the names.
+    fn create_type_names(&self) -> Result<(IndexMap<RefKey, String>, Vec<(RefKey, String)>)> {
+        let mut type_names = IndexMap::new();
+        let mut renamed_schemas = Vec::new();
+        // the first schema for each type name
+        let mut created: IndexMap<String, (&RefKey, &Schema)> = IndexMap::new();
+        let all_schemas = self.all_schemas()?;
+        for (ref_key, schema) in &all_schemas {
+            if self.is_mapped(ref_key) {
+                continue;
+            }
+            let name = get_client_name(&ref_key.name, &schema.schema.common);
+            let mut type_name = name.to_owned();
+            if let Some((first_ref_key, first_schema)) = created.get(&type_name.to_camel_case()) {
+                if self.is_same_schema(first_ref_key, first_schema, ref_key, &schema.schema)? {
+                    type_names.insert(ref_key.clone(), type_name);
+                    continue;
+                }
+                let file_stem = ref_key.file_path.file_stem().and_then(OsStr::to_str).unwrap_or_default();
+                type_name = format!("{}{}", file_stem.to_camel_case(), name.to_camel_case());
+                let mut n = 2;
+                while created.contains_key(&type_name.to_camel_case()) {
+                    type_name = format!("{}{}{}", file_stem.to_camel_case(), name.to_camel_case(), n);
+                    n += 1;
+                }
+                renamed_schemas.push((ref_key.clone(), type_name.clone()));
+            }
+            created.insert(type_name.to_camel_case(), (ref_key, &schema.schema));
+            type_names.insert(ref_key.clone(), type_name);
+        }
+        Ok((type_names, renamed_schemas))
+    }
+
+    /// Whether two definitions are the same, where their references are compared by the schemas that they resolve to,
+    /// as the same reference is to another schema in another file.
+    fn is_same_schema(&self, ref_key: &RefKey, schema: &Schema, other_ref_key: &RefKey, other_schema: &Schema) -> Result<bool> {
+        if schema != other_schema {
+            return Ok(false);
+        }
+        for (reference, other_reference) in get_schema_schema_references(schema)
+            .iter()
+            .zip(get_schema_schema_references(other_schema).iter())
+        {
+            let resolved = self.spec.schema_ref_key(&ref_key.file_path, reference).context(SpecError)?;
+            let other_resolved = self
+                .spec
+                .schema_ref_key(&other_ref_key.file_path, other_reference)
+                .context(SpecError)?;
+            if resolved != other_resolved {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Names the input models, which are created for the schemas with read-only properties, and for the schemas
+    /// with fields whose types have input models, so that nothing that the service sets can be sent.
+    /// The names are distinct from the type names, so a definition that is already named like an input model keeps its name.
+    fn create_input_type_names(&self) -> Result<IndexMap<RefKey, String>> {
+        let mut dependencies = IndexMap::new();
+        let mut has_input_model = HashSet::new();
+        for (ref_key, schema) in &self.all_schemas()? {
+            if self.is_mapped(ref_key) {
+                continue;
+            }
+            let mut schema_dependencies = Vec::new();
+            if self.get_input_model_dependencies(&ref_key.file_path, &schema.schema, &mut schema_dependencies)? {
+                has_input_model.insert(ref_key.clone());
+            }
+            dependencies.insert(ref_key.clone(), schema_dependencies);
+        }
+        loop {
+            let found: Vec<RefKey> = dependencies
+                .iter()
+                .filter(|(ref_key, schema_dependencies)| {
+                    !has_input_model.contains(*ref_key) && schema_dependencies.iter().any(|dependency| has_input_model.contains(dependency))
+                })
+                .map(|(ref_key, _)| ref_key.clone())
+                .collect();
+            if found.is_empty() {
+                break;
+            }
+            has_input_model.extend(found);
+        }
+
+        let mut names: HashSet<String> = self.type_names.values().map(|name| name.to_camel_case()).collect();
+        // identical schemas with the same name share the input model, as they share the type
+        let mut input_names: HashMap<String, String> = HashMap::new();
+        let mut input_type_names = IndexMap::new();
+        for ref_key in dependencies.keys().filter(|ref_key| has_input_model.contains(*ref_key)) {
+            let type_name = self.type_name(ref_key).to_camel_case();
+            let input_name = input_names.entry(type_name.clone()).or_insert_with(|| {
+                let mut input_name = format!("{}Input", type_name);
+                let mut n = 2;
+                while !names.insert(input_name.clone()) {
+                    input_name = format!("{}Input{}", type_name, n);
+                    n += 1;
+                }
+                input_name
+            });
+            input_type_names.insert(ref_key.clone(), input_name.clone());
+        }
+        Ok(input_type_names)
+    }
+
+    /// Gets whether a schema has read-only properties, and adds the schemas of its fields, whose input models it would use.
+    /// The properties of a local struct are fields of its own input model, so they are checked along with the schema.
+    fn get_input_model_dependencies(&self, doc_file: &Path, schema: &Schema, dependencies: &mut Vec<RefKey>) -> Result<bool> {
+        if is_array(&schema.common) {
+            if let ReferenceOr::Reference { reference, .. } = get_schema_array_items(&schema.common)? {
+                dependencies.push(self.spec.schema_ref_key(doc_file, reference).context(SpecError)?);
+            }
+            return Ok(false);
+        }
+        for all_of in &schema.all_of {
+            if let ReferenceOr::Reference { reference, .. } = all_of {
+                dependencies.push(self.spec.schema_ref_key(doc_file, reference).context(SpecError)?);
+            }
+        }
+        let mut has_read_only = false;
+        for property in schema.properties.values() {
+            match property {
+                ReferenceOr::Reference { reference, .. } => {
+                    let resolved = self.spec.resolve_schema(doc_file, property).context(SpecError)?;
+                    has_read_only |= is_read_only(&resolved.schema);
+                    dependencies.push(self.spec.schema_ref_key(doc_file, reference).context(SpecError)?);
+                }
+                ReferenceOr::Item(property) => {
+                    has_read_only |= is_read_only(property);
+                    if is_array(&property.common) {
+                        if let ReferenceOr::Reference { reference, .. } = get_schema_array_items(&property.common)? {
+                            dependencies.push(self.spec.schema_ref_key(doc_file, reference).context(SpecError)?);
+                        }
+                    } else if property.common.enum_.len() == 0 && property.properties.len() > 0 {
+                        has_read_only |= self.get_input_model_dependencies(doc_file, property, dependencies)?;
+                    }
+                }
+            }
+        }
+        Ok(has_read_only)
+    }
+
+    /// Whether a local struct has an input model, as it has read-only properties or fields whose types have input models.
+    fn has_local_input_model(&self, doc_file: &Path, schema: &Schema) -> Result<bool> {
+        let mut dependencies = Vec::new();
+        let has_read_only = self.get_input_model_dependencies(doc_file, schema, &mut dependencies)?;
+        Ok(has_read_only || dependencies.iter().any(|dependency| self.input_type_names.contains_key(dependency)))
+    }
+
+    /// Gets the schemas whose types have been renamed, as they differ from another schema with the same name, and their type names.
+    pub fn renamed_schemas(&self) -> &[(RefKey, String)] {
+        &self.renamed_schemas
+    }
+
+    /// Gets the schemas that models are created for, which are all the definitions in the input files
+    /// and the schemas that they reference in other files. When pruning models, it is only those reachable from the operations.
+    fn all_schemas(&self) -> Result<IndexMap<RefKey, ResolvedSchema>> {
+        let mut all_schemas = self.all_definitions()?;
+        if let Some(reachable) = &self.reachable_schemas {
+            all_schemas.retain(|ref_key, _| reachable.contains(ref_key));
+        }
+        Ok(all_schemas)
+    }
+
+    /// Gets the schemas that are not created as models, because they are not reachable from the operations.
+    pub fn pruned_schemas(&self) -> Result<Vec<RefKey>> {
+        let reachable = match &self.reachable_schemas {
+            Some(reachable) => reachable,
+            None => return Ok(Vec::new()),
+        };
+        Ok(self
+            .all_definitions()?
+            .into_iter()
+            .map(|(ref_key, _)| ref_key)
+            .filter(|ref_key| !reachable.contains(ref_key) && !self.is_mapped(ref_key))
+            .collect())
+    }
+
+    /// Gets the schemas that the operations use, along with the schemas that derive from a reachable schema with
+    /// a discriminator, as they may be returned in its place.
+    fn get_reachable_schemas(&self, all_definitions: &IndexMap<RefKey, ResolvedSchema>) -> Result<IndexSet<RefKey>> {
+        let mut reachable: IndexSet<RefKey> = self.group_schemas.values().flatten().cloned().collect();
+        // the reachable polymorphic schemas are those with a discriminator and the ones that derive from them
+        let mut polymorphic = HashSet::new();
+        loop {
+            let mut found = false;
+            for (ref_key, schema) in all_definitions {
+                if polymorphic.contains(ref_key) {
+                    continue;
+                }
+                let is_derived = schema.schema.all_of.iter().any(|parent| match parent {
+                    ReferenceOr::Reference { reference, .. } => match self.spec.schema_ref_key(&ref_key.file_path, reference) {
+                        Ok(parent) => polymorphic.contains(&parent),
+                        Err(_) => false,
+                    },
+                    ReferenceOr::Item(_) => false,
+                });
+                if is_derived || (schema.schema.discriminator.is_some() && reachable.contains(ref_key)) {
+                    polymorphic.insert(ref_key.clone());
+                    if reachable.insert(ref_key.clone()) {
+                        for reference in get_schema_schema_references(&schema.schema) {
+                            self.add_referenced_schemas(&mut reachable, &ref_key.file_path, &reference)?;
+                        }
+                    }
+                    found = true;
+                }
+            }
+            if !found {
+                break;
+            }
+        }
+        Ok(reachable)
+    }
+
+    /// Gets all the definitions in the input files and the schemas that they reference in other files.
+    fn all_definitions(&self) -> Result<IndexMap<RefKey, ResolvedSchema>> {
         let mut all_schemas: IndexMap<RefKey, ResolvedSchema> = IndexMap::new();
 
         // all definitions from input_files
@@ -68,97 +627,405 @@ impl CodeGen {
             }
         }
 
-        // any referenced schemas from other files
+        // any referenced schemas from other files, also through the parameters of other files
         for (doc_file, doc) in self.spec.input_docs() {
             for reference in openapi::get_api_schema_references(doc) {
                 self.add_schema_refs(&mut all_schemas, doc_file, reference)?;
             }
+            for reference in openapi::get_api_parameter_references(doc) {
+                let parameter = self.spec.resolve_parameter_ref(doc_file, reference).context(SpecError)?;
+                for reference in get_parameter_schema_references(&parameter) {
+                    self.add_schema_refs(&mut all_schemas, doc_file, reference)?;
+                }
+            }
+        }
+        Ok(all_schemas)
+    }
+
+    /// Gets the schemas that the operations of each operation group use, which are the schemas of their parameters
+    /// and responses, and all of the schemas that those reference. Operations without a group are under `None`.
+    fn get_operation_group_schemas(&self) -> Result<IndexMap<Option<String>, IndexSet<RefKey>>> {
+        let mut group_schemas: IndexMap<Option<String>, IndexSet<RefKey>> = IndexMap::new();
+        for (doc_file, doc) in self.spec.input_docs() {
+            let paths = self.spec.resolve_path_map(doc_file, &doc.paths).context(SpecError)?;
+            for (path, item) in &paths {
+                for op in spec::path_item_operations(item) {
+                    let (module_name, _function_name) = op.function_name(path);
+                    let schemas = group_schemas.entry(module_name).or_default();
+                    let parameters = self
+                        .spec
+                        .resolve_parameters(doc_file, &op.operation().parameters)
+                        .context(SpecError)?;
+                    let responses = op.operation().responses.values().filter_map(|response| response.schema.as_ref());
+                    for schema in parameters.iter().filter_map(|parameter| parameter.schema.as_ref()).chain(responses) {
+                        match schema {
+                            ReferenceOr::Reference { reference, .. } => self.add_referenced_schemas(schemas, doc_file, reference)?,
+                            ReferenceOr::Item(schema) => {
+                                for reference in get_schema_schema_references(schema) {
+                                    self.add_referenced_schemas(schemas, doc_file, &reference)?;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(group_schemas)
+    }
+
+    /// Adds a referenced schema and all of the schemas that it references.
+    fn add_referenced_schemas(&self, schemas: &mut IndexSet<RefKey>, doc_file: &Path, reference: &Reference) -> Result<()> {
+        let ref_key = self.spec.schema_ref_key(doc_file, reference).context(SpecError)?;
+        if let Some(schema) = self.spec.schema(&ref_key) {
+            if schemas.insert(ref_key.clone()) {
+                for reference in get_schema_schema_references(schema) {
+                    self.add_referenced_schemas(schemas, &ref_key.file_path, &reference)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the cargo feature of an operation group, when the operation groups are features.
+    /// Operations without a group are only enabled by the feature of the whole module.
+    fn get_group_feature(&self, module_name: Option<&str>) -> Option<String> {
+        let feature = self.config.feature_name.as_ref()?;
+        match module_name {
+            Some(module_name) => Some(format!("{}-{}", feature, module_name.replace('_', "-"))),
+            None => Some(feature.clone()),
+        }
+    }
+
+    /// Gets the cargo features of the operation groups, when the operation groups are features.
+    pub fn group_features(&self) -> Vec<String> {
+        self.group_schemas
+            .keys()
+            .flatten()
+            .filter_map(|module_name| self.get_group_feature(Some(module_name)))
+            .collect()
+    }
+
+    /// Gets the `cfg` of each type, by its name, that enables it along with the operation groups that use it.
+    /// There are none unless the operation groups are features.
+    fn create_type_cfgs(&self, all_schemas: &IndexMap<RefKey, ResolvedSchema>) -> HashMap<String, TokenStream> {
+        let mut type_cfgs = HashMap::new();
+        let feature = match &self.config.feature_name {
+            Some(feature) => feature,
+            None => return type_cfgs,
+        };
+        let mut type_features: HashMap<&str, IndexSet<String>> = HashMap::new();
+        for ref_key in all_schemas.keys() {
+            type_features.entry(self.type_name(ref_key)).or_default().insert(feature.clone());
+        }
+        for (module_name, schemas) in &self.group_schemas {
+            let group_feature = self.get_group_feature(module_name.as_deref()).unwrap_or_default();
+            for ref_key in schemas {
+                type_features
+                    .entry(self.type_name(ref_key))
+                    .or_default()
+                    .insert(group_feature.clone());
+            }
+        }
+        for (type_name, features) in type_features {
+            let features = features.iter();
+            type_cfgs.insert(type_name.to_owned(), quote! { #[cfg(any(#(feature = #features),*))] });
+        }
+        type_cfgs
+    }
+
+    /// Creates the models, where the models from each specification file are a part of the module.
+    pub fn create_models(&self) -> Result<ModuleCode> {
+        let mut file = TokenStream::new();
+        file.extend(create_generated_by_header());
+        file.extend(quote! {
+            #![allow(non_camel_case_types)]
+            #![allow(unused_imports)]
+            #![allow(deprecated)]
+            use serde::{Deserialize, Serialize};
+        });
+        if self.config.validation {
+            file.extend(create_validation_module());
+        }
+        if self.config.resource_ids {
+            let mut resource_ids = Vec::new();
+            for (type_name, pattern) in self.resource_ids.values() {
+                resource_ids.push(resource_id::create_resource_id(type_name, pattern).context(ResourceIdError)?);
+            }
+            file.extend(resource_id::create_resource_ids_module(resource_ids));
+        }
+        let all_schemas = self.all_schemas()?;
+
+        // the parts are named after the files, and must not be named like the modules of the types
+        let mut part_names = UniqueIdents::default();
+        for name in std::iter::once("mod").chain(self.type_names.values().map(String::as_str)) {
+            part_names
+                .insert(ident(&name.to_snake_case()).context(IdentError {
+                    file: file!(),
+                    line: line!(),
+                })?)
+                .context(IdentError {
+                    file: file!(),
+                    line: line!(),
+                })?;
         }
+        let mut parts: IndexMap<&Path, ModulePart> = IndexMap::new();
+        let type_cfgs = self.create_type_cfgs(&all_schemas);
 
-        let mut schema_names = IndexMap::new();
+        // identical schemas with the same name are only created once
+        let mut type_names = HashSet::new();
         for (ref_key, schema) in &all_schemas {
+            if self.is_mapped(ref_key) {
+                continue;
+            }
             let doc_file = &ref_key.file_path;
-            let schema_name = &ref_key.name;
-            if let Some(_first_doc_file) = schema_names.insert(schema_name, doc_file) {
-                // eprintln!(
-                //     "WARN schema {} already created from {:?}, duplicate from {:?}",
-                //     schema_name, first_doc_file, doc_file
-                // );
-            } else {
+            let type_name = self.type_name(ref_key);
+            if type_names.insert(type_name) {
+                if !parts.contains_key(doc_file.as_path()) {
+                    let file_stem = doc_file.file_stem().and_then(OsStr::to_str).unwrap_or_default();
+                    let name = ident(&file_stem.to_snake_case()).context(IdentError {
+                        file: file!(),
+                        line: line!(),
+                    })?;
+                    let name = part_names.insert(name).context(IdentError {
+                        file: file!(),
+                        line: line!(),
+                    })?;
+                    parts.insert(
+                        doc_file,
+                        ModulePart {
+                            name,
+                            attributes: TokenStream::new(),
+                            is_public: false,
+                            content: TokenStream::new(),
+                        },
+                    );
+                }
+                let mut file = TokenStream::new();
                 if is_array(&schema.schema.common) {
-                    file.extend(self.create_vec_alias(doc_file, schema_name, schema)?);
+                    file.extend(self.create_vec_alias(doc_file, ref_key, type_name, schema)?);
                 } else if is_local_enum(schema) {
                     let no_namespace = TokenStream::new();
-                    let (_tp_name, tp) = create_enum(&no_namespace, schema_name, schema)?;
+                    let model = self.get_model_attributes(Some(&ref_key.name), TypeTraits::ALL);
+                    let deprecation = self.spec.schema_deprecation(ref_key);
+                    let (_tp_name, tp) = self.create_enum(&no_namespace, type_name, schema, deprecation, &model)?;
                     file.extend(tp);
                 } else {
-                    for stream in self.create_struct(doc_file, schema_name, schema)? {
+                    let input_type_name = self.input_type_names.get(ref_key).map(String::as_str);
+                    for stream in self.create_struct(doc_file, Some(&ref_key.name), &ref_key.name, type_name, input_type_name, schema)? {
                         file.extend(stream);
                     }
                 }
+                if let Some(cfg) = type_cfgs.get(type_name) {
+                    file = add_item_attribute(file, cfg)?;
+                }
+                parts[doc_file.as_path()].content.extend(file);
             }
         }
-        Ok(file)
+        Ok(ModuleCode {
+            header: file,
+            parts: parts.into_iter().map(|(_, part)| part).collect(),
+        })
     }
 
-    pub fn create_operations(&self) -> Result<TokenStream> {
+    /// Creates the operations, where each operation group is a part of the module.
+    pub fn create_operations(&self) -> Result<ModuleCode> {
         let mut file = TokenStream::new();
         file.extend(create_generated_by_header());
         file.extend(quote! {
             #![allow(unused_mut)]
             #![allow(unused_variables)]
             #![allow(unused_imports)]
+            #![allow(deprecated)]
             use crate::models::*;
             use snafu::{ResultExt, Snafu};
         });
         let param_re = Regex::new(r"\{(\w+)\}").unwrap();
-        let mut modules: IndexMap<Option<String>, TokenStream> = IndexMap::new();
         // println!("input_files {:?}", self.input_files());
+        let mut doc_paths = Vec::new();
+        // the descriptions of the tags document the modules with the same name
+        let mut tag_descriptions = HashMap::new();
         for (doc_file, doc) in self.spec.docs() {
             // only operations from listed input files
             // println!("doc_file {:?}", doc_file);
             if self.spec.is_input_file(&doc_file) {
                 let paths = self.spec.resolve_path_map(doc_file, &doc.paths).context(SpecError)?;
-                for (path, item) in &paths {
-                    for op in spec::path_item_operations(item) {
-                        let (module_name, function_name) = op.function_name(path);
-                        let function = create_function(self, doc_file, path, item, &op, &param_re, &function_name)?;
-                        if modules.contains_key(&module_name) {}
-                        match modules.get_mut(&module_name) {
-                            Some(module) => {
-                                module.extend(function);
-                            }
-                            None => {
-                                let mut module = TokenStream::new();
-                                module.extend(function);
-                                modules.insert(module_name, module);
-                            }
+                doc_paths.push((doc_file, paths));
+                for tag in &doc.tags {
+                    if let Some(description) = &tag.description {
+                        tag_descriptions.entry(tag.name.to_snake_case()).or_insert(description.as_str());
+                    }
+                }
+            }
+        }
+
+        // the modules are named first, as a function at the top level also has a module with its name
+        let mut top_level_names = UniqueIdents::default();
+        if self.config.split_files {
+            // a module named `mod` would be written to `mod.rs`
+            top_level_names.insert(quote! { r#mod }).context(IdentError {
+                file: file!(),
+                line: line!(),
+            })?;
+        }
+        let mut module_names: IndexMap<String, TokenStream> = IndexMap::new();
+        for (_doc_file, paths) in &doc_paths {
+            for (path, item) in paths {
+                for op in spec::path_item_operations(item) {
+                    if let (Some(module_name), _) = op.function_name(path) {
+                        if !module_names.contains_key(&module_name) {
+                            let name = ident(&module_name).context(IdentError {
+                                file: file!(),
+                                line: line!(),
+                            })?;
+                            let name = top_level_names.insert(name).context(IdentError {
+                                file: file!(),
+                                line: line!(),
+                            })?;
+                            module_names.insert(module_name, name);
                         }
                     }
                 }
             }
         }
-        for (module_name, module) in modules {
-            match module_name {
-                Some(module_name) => {
-                    let name = ident(&module_name).context(IdentError {
+
+        // the functions are named before they are created, so that a deprecated operation can name its replacement
+        let mut fnames = Vec::new();
+        let mut operation_paths = HashMap::new();
+        let mut function_names: HashMap<Option<String>, UniqueIdents> = HashMap::new();
+        let mut renamed_functions = Vec::new();
+        for (doc_file, paths) in &doc_paths {
+            for (path, item) in paths {
+                for op in spec::path_item_operations(item) {
+                    let (module_name, function_name) = op.function_name(path);
+                    let fname = ident(&function_name).context(IdentError {
                         file: file!(),
                         line: line!(),
                     })?;
-                    file.extend(quote! {
-                        pub mod #name {
+                    let names = match module_name {
+                        Some(_) => function_names.entry(module_name.clone()).or_default(),
+                        None => &mut top_level_names,
+                    };
+                    let unique_fname = names.insert(fname.clone()).context(IdentError {
+                        file: file!(),
+                        line: line!(),
+                    })?;
+                    let operation_path = match &module_name {
+                        Some(module_name) => {
+                            let module_name = &module_names[module_name];
+                            quote! { #module_name::#unique_fname }
+                        }
+                        None => quote! { #unique_fname },
+                    };
+                    let operation_path = operation_path.to_string().replace(' ', "");
+                    if unique_fname.to_string() != fname.to_string() {
+                        renamed_functions.push(RenamedFunction {
+                            file_path: doc_file.to_path_buf(),
+                            operation: match &op.operation().operation_id {
+                                Some(operation_id) => operation_id.clone(),
+                                None => format!("{} {}", op.verb_name().to_uppercase(), path),
+                            },
+                            function_path: operation_path.clone(),
+                        });
+                    }
+                    if let Some(operation_id) = &op.operation().operation_id {
+                        operation_paths.insert(operation_id.to_lowercase(), operation_path);
+                    }
+                    fnames.push(unique_fname);
+                }
+            }
+        }
+        *self.renamed_functions.borrow_mut() = renamed_functions;
+
+        // the functions that take a resource ID are named last, so that the other functions keep their names
+        let mut id_fnames = Vec::new();
+        for (_doc_file, paths) in &doc_paths {
+            for (path, item) in paths {
+                for op in spec::path_item_operations(item) {
+                    let id_fname = match self.get_resource_id_for_path(path) {
+                        Some(resource_id) => {
+                            let (module_name, _function_name) = op.function_name(path);
+                            let fname = fnames[id_fnames.len()].to_string();
+                            let id_fname = ident(&format!("{}_by_id", fname.trim_start_matches("r#"))).context(IdentError {
+                                file: file!(),
+                                line: line!(),
+                            })?;
+                            let names = match module_name {
+                                Some(_) => function_names.entry(module_name).or_default(),
+                                None => &mut top_level_names,
+                            };
+                            let id_fname = names.insert(id_fname).context(IdentError {
+                                file: file!(),
+                                line: line!(),
+                            })?;
+                            Some((id_fname, resource_id))
+                        }
+                        None => None,
+                    };
+                    id_fnames.push(id_fname);
+                }
+            }
+        }
+
+        let mut modules: IndexMap<Option<String>, TokenStream> = IndexMap::new();
+        // a module is deprecated when all of its operations are
+        let mut deprecated_modules: HashMap<Option<String>, bool> = HashMap::new();
+        let mut fnames = fnames.iter().zip(&id_fnames);
+        for (doc_file, paths) in &doc_paths {
+            for (path, item) in paths {
+                for op in spec::path_item_operations(item) {
+                    let (module_name, _function_name) = op.function_name(path);
+                    let (fname, id_fname) = fnames.next().expect("a name for each function");
+                    let resource_id = id_fname.as_ref().map(|(id_fname, resource_id)| (id_fname, *resource_id));
+                    let deprecation = self.spec.operation_deprecation(doc_file, path, &op);
+                    let is_deprecated = deprecation.is_some() || is_deprecated_operation(op.operation());
+                    let deprecated = if is_deprecated {
+                        create_deprecated(get_operation_deprecation_note(op.operation(), deprecation, &operation_paths).as_deref())
+                    } else {
+                        TokenStream::new()
+                    };
+                    *deprecated_modules.entry(module_name.clone()).or_insert(true) &= is_deprecated;
+                    let function = create_function(self, doc_file, path, item, &op, &param_re, fname, &deprecated, resource_id)?;
+                    modules.entry(module_name).or_default().extend(function);
+                }
+            }
+        }
+        let mut parts = Vec::new();
+        for (module_name, module) in modules {
+            match module_name {
+                Some(module_name) => {
+                    let name = &module_names[&module_name];
+                    let doc = create_doc(tag_descriptions.get(&module_name.to_snake_case()).copied().unwrap_or_default());
+                    let deprecated = if deprecated_modules[&Some(module_name.clone())] {
+                        create_deprecated(Some("all of the operations are deprecated"))
+                    } else {
+                        TokenStream::new()
+                    };
+                    let cfg = match self.get_group_feature(Some(&module_name)) {
+                        Some(feature) => quote! { #[cfg(feature = #feature)] },
+                        None => TokenStream::new(),
+                    };
+                    parts.push(ModulePart {
+                        name: name.clone(),
+                        attributes: quote! {
+                            #cfg
+                            #doc
+                            #deprecated
+                        },
+                        is_public: true,
+                        content: quote! {
                             use crate::models::*;
                             use snafu::{ResultExt, Snafu};
                             #module
-                        }
+                        },
                     });
                 }
-                None => {
-                    file.extend(module);
-                }
+                None => match self.get_group_feature(None) {
+                    Some(feature) => file.extend(add_item_attribute(module, &quote! { #[cfg(feature = #feature)] })?),
+                    None => file.extend(module),
+                },
             }
         }
-        Ok(file)
+        Ok(ModuleCode { header: file, parts })
     }
 
     // For create_models. Recursively adds schema refs.
@@ -178,40 +1045,273 @@ impl CodeGen {
         Ok(())
     }
 
-    fn create_vec_alias(&self, _doc_file: &Path, alias_name: &str, schema: &ResolvedSchema) -> Result<TokenStream> {
+    fn create_vec_alias(&self, doc_file: &Path, ref_key: &RefKey, alias_name: &str, schema: &ResolvedSchema) -> Result<TokenStream> {
         let items = get_schema_array_items(&schema.schema.common)?;
         let typ = ident(&alias_name.to_camel_case()).context(IdentError {
             file: file!(),
             line: line!(),
         })?;
-        let items_typ = get_type_name_for_schema_ref(&items, AsReference::False)?;
-        Ok(quote! { pub type #typ = Vec<#items_typ>; })
+        let items_typ = get_type_name_for_schema_ref(self, doc_file, &items, AsReference::False)?;
+        let doc = create_schema_doc(&schema.schema.common);
+        let deprecation = self.spec.schema_deprecation(ref_key);
+        let deprecated = create_schema_deprecated(deprecation, schema.schema.common.description.as_deref());
+        let mut alias = quote! {
+            #doc
+            #deprecated
+            pub type #typ = Vec<#items_typ>;
+        };
+        // the input model of a list is the list of the input models of its items
+        let input_typ = self.get_input_type_name(ref_key)?;
+        if let (Some(input_typ), Some(input_items_typ)) = (input_typ, self.get_input_type_name_for_schema_ref(doc_file, items)?) {
+            alias.extend(quote! {
+                #doc
+                #deprecated
+                pub type #input_typ = Vec<#input_items_typ>;
+            });
+        }
+        Ok(alias)
     }
 
-    fn create_struct(&self, doc_file: &Path, struct_name: &str, schema: &ResolvedSchema) -> Result<Vec<TokenStream>> {
+    /// Creates a struct for a schema, where `struct_name` is the name in the specification and `type_name` the name for the type.
+    /// The `schema_name` is the name of the definition, which there is not for a local struct.
+    /// When there is an `input_type_name`, the input model is created too.
+    fn create_struct(
+        &self,
+        doc_file: &Path,
+        schema_name: Option<&str>,
+        struct_name: &str,
+        type_name: &str,
+        input_type_name: Option<&str>,
+        schema: &ResolvedSchema,
+    ) -> Result<Vec<TokenStream>> {
         // println!("create_struct {} {}", doc_file.to_str().unwrap(), struct_name);
         let mut streams = Vec::new();
-        let mut local_types = Vec::new();
-        let mut props = TokenStream::new();
-        let ns = ident(&struct_name.to_snake_case()).context(IdentError {
+        let ns = ident(&type_name.to_snake_case()).context(IdentError {
+            file: file!(),
+            line: line!(),
+        })?;
+        let nm = ident(&type_name.to_camel_case()).context(IdentError {
             file: file!(),
             line: line!(),
         })?;
-        let nm = ident(&struct_name.to_camel_case()).context(IdentError {
+        let (fields, mut local_types) = self.create_struct_fields(doc_file, struct_name, type_name, schema)?;
+        let doc = create_schema_doc(&schema.schema.common);
+        let deprecation = schema_name.and_then(|name| {
+            self.spec.schema_deprecation(&RefKey {
+                file_path: doc_file.to_owned(),
+                name: name.to_owned(),
+            })
+        });
+        let deprecated = create_schema_deprecated(deprecation, schema.schema.common.description.as_deref());
+        let mut visited = HashSet::new();
+        if let Some(name) = schema_name {
+            visited.insert(RefKey {
+                file_path: doc_file.to_owned(),
+                name: name.to_owned(),
+            });
+        }
+        let traits = self.get_schema_traits(doc_file, schema_name, &schema.schema, &mut visited)?;
+        let model = self.get_model_attributes(schema_name, traits);
+
+        let attributes = quote! {
+            #doc
+            #deprecated
+        };
+        let wire_nm = quote! { Wire };
+        self.create_struct_items(
+            &nm,
+            &ns,
+            &wire_nm,
+            &fields,
+            &attributes,
+            &model,
+            traits.default,
+            &mut streams,
+            &mut local_types,
+        );
+
+        // a separate request shape without the fields that the service sets, where the other fields
+        // have the request shapes of their types, including the fields of a flattened property
+        if let Some(input_type_name) = input_type_name {
+            let input_nm = ident(input_type_name).context(IdentError {
+                file: file!(),
+                line: line!(),
+            })?;
+            let mut input_fields = Vec::new();
+            for field in fields.iter().filter(|field| !field.is_read_only) {
+                let mut input_field = field.to_input();
+                if let Some(flattened) = &mut input_field.flattened {
+                    if let Some(input_type_name) = &flattened.input_type_name {
+                        flattened.type_name = input_type_name.clone();
+                        flattened.fields = flattened
+                            .fields
+                            .iter()
+                            .filter(|field| !field.is_read_only)
+                            .map(StructFieldCode::to_input)
+                            .collect();
+                        if flattened.fields.is_empty() {
+                            continue;
+                        }
+                    }
+                }
+                input_fields.push(input_field);
+            }
+            let wire_nm = quote! { InputWire };
+            self.create_struct_items(
+                &input_nm,
+                &ns,
+                &wire_nm,
+                &input_fields,
+                &attributes,
+                &model,
+                traits.default,
+                &mut streams,
+                &mut local_types,
+            );
+        }
+
+        if local_types.len() > 0 {
+            let mut types = TokenStream::new();
+            local_types.into_iter().for_each(|tp| types.extend(tp));
+            streams.push(quote! {
+                pub mod #ns {
+                    use super::*;
+                    #types
+                }
+            });
+        }
+
+        Ok(streams)
+    }
+
+    /// Creates a struct with its `Debug`, constructor and `validate`.
+    /// When some of the fields are flattened, the struct has the flattened shape and is serialized through
+    /// the local struct `wire_nm`, which has the nested shape.
+    #[allow(clippy::too_many_arguments)]
+    fn create_struct_items(
+        &self,
+        nm: &TokenStream,
+        ns: &TokenStream,
+        wire_nm: &TokenStream,
+        fields: &[StructFieldCode],
+        attributes: &TokenStream,
+        model: &ModelAttributesCode,
+        is_default: bool,
+        streams: &mut Vec<TokenStream>,
+        local_types: &mut Vec<TokenStream>,
+    ) {
+        if fields.iter().any(|field| field.flattened.is_some()) {
+            let flat_fields = flatten_fields(fields);
+            let props: Vec<_> = flat_fields.iter().map(StructFieldCode::declaration).collect();
+            let wire = quote! { #ns::#wire_nm }.to_string().replace(' ', "");
+            let flat_fields_ref: Vec<_> = flat_fields.iter().collect();
+            let derive = create_struct_derive(&flat_fields_ref, model);
+            streams.push(quote! {
+                #attributes
+                #derive
+                #[serde(from = #wire, into = #wire)]
+                pub struct #nm {
+                    #(#props)*
+                }
+            });
+            streams.push(create_struct_debug(nm, &flat_fields_ref));
+            streams.push(create_struct_constructor(nm, &flat_fields_ref, is_default));
+            if self.config.validation {
+                streams.push(quote! {
+                    impl #nm {
+                        pub fn validate(&self) -> std::result::Result<(), model_validation::ValidationError> {
+                            #ns::#wire_nm::from(self.clone()).validate()
+                        }
+                    }
+                });
+            }
+            let wire_props: Vec<_> = fields.iter().map(StructFieldCode::declaration).collect();
+            let fields_ref: Vec<_> = fields.iter().collect();
+            let derive = create_struct_derive(&fields_ref, &ModelAttributesCode::default());
+            local_types.push(quote! {
+                #derive
+                pub struct #wire_nm {
+                    #(#wire_props)*
+                }
+            });
+            local_types.push(create_struct_debug(wire_nm, &fields_ref));
+            if self.config.validation {
+                local_types.push(create_struct_validate(wire_nm, &fields_ref));
+            }
+            local_types.push(create_struct_conversions(nm, wire_nm, fields, &flat_fields));
+        } else {
+            let props: Vec<_> = fields.iter().map(StructFieldCode::declaration).collect();
+            let fields_ref: Vec<_> = fields.iter().collect();
+            let derive = create_struct_derive(&fields_ref, model);
+            streams.push(quote! {
+                #attributes
+                #derive
+                pub struct #nm {
+                    #(#props)*
+                }
+            });
+            streams.push(create_struct_debug(nm, &fields_ref));
+            streams.push(create_struct_constructor(nm, &fields_ref, is_default));
+            if self.config.validation {
+                streams.push(create_struct_validate(nm, &fields_ref));
+            }
+        }
+    }
+
+    /// Creates the fields of a struct as they are serialized, along with the types that are local to the struct.
+    fn create_struct_fields(
+        &self,
+        doc_file: &Path,
+        struct_name: &str,
+        type_name: &str,
+        schema: &ResolvedSchema,
+    ) -> Result<(Vec<StructFieldCode>, Vec<TokenStream>)> {
+        let mut local_types = Vec::new();
+        let mut fields = Vec::new();
+        let ns = ident(&type_name.to_snake_case()).context(IdentError {
             file: file!(),
             line: line!(),
         })?;
         let required: HashSet<&str> = schema.schema.required.iter().map(String::as_str).collect();
 
+        // distinct names may map to the same field or local type name
+        let mut field_names = UniqueIdents::default();
+        let mut local_type_names = UniqueIdents::default();
+        for wire_name in &[quote! { Wire }, quote! { InputWire }] {
+            local_type_names.insert(wire_name.clone()).context(IdentError {
+                file: file!(),
+                line: line!(),
+            })?;
+        }
+
         for schema in &schema.schema.all_of {
-            let type_name = get_type_name_for_schema_ref(schema, AsReference::False)?;
+            let type_name = get_type_name_for_schema_ref(self, doc_file, schema, AsReference::False)?;
+            let input_type_name = self.get_input_type_name_for_schema_ref(doc_file, schema)?;
             let field_name = ident(&type_name.to_string().to_snake_case()).context(IdentError {
                 file: file!(),
                 line: line!(),
             })?;
-            props.extend(quote! {
-                #[serde(flatten)]
-                pub #field_name: #type_name,
+            let field_name = field_names.insert(field_name).context(IdentError {
+                file: file!(),
+                line: line!(),
+            })?;
+            let validation = if self.config.validation && is_struct(&self.spec.resolve_schema(doc_file, schema).context(SpecError)?) {
+                quote! { self.#field_name.validate()?; }
+            } else {
+                quote! {}
+            };
+            fields.push(StructFieldCode {
+                name: field_name,
+                type_name,
+                doc: quote! {},
+                deprecated: quote! {},
+                input_type_name,
+                serde: quote! { #[serde(flatten)] },
+                is_read_only: false,
+                is_secret: false,
+                initializer: None,
+                validation,
+                flattened: None,
             });
         }
 
@@ -220,22 +1320,48 @@ impl CodeGen {
             .resolve_schema_map(doc_file, &schema.schema.properties)
             .context(SpecError)?;
         for (property_name, property) in &properties {
-            let nm = ident(&property_name.to_snake_case()).context(IdentError {
+            // the client name of a referenced schema is the name of its type, not of the field,
+            // which has the `x-ms-client-name` next to its `$ref`
+            let field_name = match property.ref_key {
+                Some(_) => self
+                    .spec
+                    .reference_client_name(
+                        &RefKey {
+                            file_path: doc_file.to_owned(),
+                            name: struct_name.to_owned(),
+                        },
+                        property_name,
+                    )
+                    .unwrap_or(property_name),
+                None => get_client_name(property_name, &property.schema.common),
+            };
+            let nm = ident(&field_name.to_snake_case()).context(IdentError {
+                file: file!(),
+                line: line!(),
+            })?;
+            let nm = field_names.insert(nm).context(IdentError {
                 file: file!(),
                 line: line!(),
             })?;
-            let (mut field_tp_name, field_tp) = self.create_struct_field_type(doc_file, &ns, property_name, property)?;
+            let (mut field_tp_name, mut input_tp_name, field_tp) =
+                self.create_struct_field_type(doc_file, &ns, &mut local_type_names, property_name, property)?;
             let is_required = required.contains(property_name.as_str());
             let is_vec = is_vec(&field_tp_name);
+            let initializer = self.create_field_initializer(doc_file, struct_name, property_name, property, &field_tp_name, is_required)?;
+            let bag_type_name = field_tp_name.clone();
+            let bag_input_type_name = input_tp_name.clone();
             if !is_vec {
                 field_tp_name = require(is_required, field_tp_name);
+                input_tp_name = input_tp_name.map(|input_tp_name| require(is_required, input_tp_name));
             }
             local_types.extend(field_tp);
+            let is_read_only = is_read_only(&property.schema);
+            let property_description = get_property_description(&schema.schema.properties[property_name], &property.schema.common);
             let mut serde_attrs: Vec<TokenStream> = Vec::new();
             if &nm.to_string() != property_name {
                 serde_attrs.push(quote! { rename = #property_name });
             }
-            if property.schema.read_only == Some(true) {
+            if is_read_only {
                 serde_attrs.push(quote! { skip_serializing });
             } else {
                 if !is_required {
@@ -252,78 +1378,254 @@ impl CodeGen {
                 quote! {}
             };
             // see if a field shoud be wrapped in a Box
-            let prop_nm = &PropertyName {
-                file_path: PathBuf::from(doc_file),
-                schema_name: struct_name.to_owned(),
-                property_name: property_name.to_string(),
-            };
-            // println!("property {:?}", prop_nm);
-            if self.config.box_properties.contains(prop_nm) {
+            let is_boxed = self.is_boxed(doc_file, struct_name, property_name);
+            if is_boxed {
                 field_tp_name = quote! { Box<#field_tp_name> };
+                input_tp_name = input_tp_name.map(|input_tp_name| quote! { Box<#input_tp_name> });
             }
-            props.extend(quote! {
-                #serde
-                pub #nm: #field_tp_name,
+            let validation = if self.config.validation {
+                let checks = self.create_property_checks(doc_file, property_name, property)?;
+                if checks.is_empty() {
+                    checks
+                } else if is_required || is_vec {
+                    quote! { let value = &self.#nm; #checks }
+                } else {
+                    quote! {
+                        if let Some(value) = &self.#nm {
+                            #checks
+                        }
+                    }
+                }
+            } else {
+                quote! {}
+            };
+            // only the fields of referenced schemas are flattened, as those are created at the top level
+            let flattened = match &property.ref_key {
+                Some(ref_key)
+                    if !is_boxed
+                        && !self.is_mapped(ref_key)
+                        && is_client_flatten(&schema.schema.properties[property_name])
+                        && is_struct(property) =>
+                {
+                    let (bag_fields, _) =
+                        self.create_struct_fields(&ref_key.file_path, &ref_key.name, self.type_name(ref_key), property)?;
+                    Some(FlattenedCode {
+                        property_name: property_name.clone(),
+                        type_name: bag_type_name,
+                        input_type_name: bag_input_type_name,
+                        is_required,
+                        fields: flatten_fields(&bag_fields),
+                    })
+                }
+                _ => None,
+            };
+            fields.push(StructFieldCode {
+                name: nm,
+                type_name: field_tp_name,
+                input_type_name: input_tp_name,
+                doc: create_doc(property_description.unwrap_or_default()),
+                deprecated: create_schema_deprecated(
+                    self.spec.property_deprecation(
+                        &RefKey {
+                            file_path: doc_file.to_owned(),
+                            name: struct_name.to_owned(),
+                        },
+                        property_name,
+                    ),
+                    property_description,
+                ),
+                serde,
+                is_read_only,
+                is_secret: is_secret(&property.schema),
+                initializer,
+                validation,
+                flattened,
             });
         }
 
-        let st = quote! {
-            #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-            pub struct #nm {
-                #props
+        // a flattened property is kept nested if any of its fields would have the same name as another field
+        let mut names: HashSet<String> = fields.iter().map(|field| field.name.to_string()).collect();
+        for field in &mut fields {
+            if let Some(flattened) = &field.flattened {
+                let flattened_names: Vec<_> = flattened.fields.iter().map(|field| field.name.to_string()).collect();
+                if flattened.fields.len() == 0 || flattened_names.iter().any(|name| names.contains(name)) {
+                    self.unflattened_properties.borrow_mut().insert(PropertyName {
+                        file_path: doc_file.to_owned(),
+                        schema_name: struct_name.to_owned(),
+                        property_name: flattened.property_name.clone(),
+                    });
+                    field.flattened = None;
+                } else {
+                    names.extend(flattened_names);
+                }
             }
+        }
+
+        Ok((fields, local_types))
+    }
+
+    /// Whether a property of a struct is configured to be wrapped in a `Box`.
+    fn is_boxed(&self, doc_file: &Path, struct_name: &str, property_name: &str) -> bool {
+        self.config.box_properties.contains(&PropertyName {
+            file_path: PathBuf::from(doc_file),
+            schema_name: struct_name.to_owned(),
+            property_name: property_name.to_owned(),
+        })
+    }
+
+    /// Creates the expression that initializes a field in the constructor, given the type of the field before it is made optional.
+    /// There is none for a constructor parameter: a required property that is an array or boxed,
+    /// or that has no default or constant value of a supported type.
+    fn create_field_initializer(
+        &self,
+        doc_file: &Path,
+        struct_name: &str,
+        property_name: &str,
+        property: &ResolvedSchema,
+        field_tp_name: &TokenStream,
+        is_required: bool,
+    ) -> Result<Option<TokenStream>> {
+        // a required constant is always set to its value
+        let default_value = match create_constant_value(property, field_tp_name)? {
+            Some(value) if is_required => Some(value),
+            _ => create_default_value(property, field_tp_name)?,
         };
-        streams.push(TokenStream::from(st));
+        let is_boxed = self.is_boxed(doc_file, struct_name, property_name);
+        Ok(match (is_required, is_vec(field_tp_name), default_value) {
+            (false, true, _) => Some(quote! { Vec::new() }),
+            (false, false, Some(value)) if !is_boxed => Some(quote! { Some(#value) }),
+            (false, false, _) => Some(quote! { None }),
+            (true, false, Some(value)) if !is_boxed => Some(value),
+            (true, _, _) => None,
+        })
+    }
 
-        if local_types.len() > 0 {
-            let mut types = TokenStream::new();
-            local_types.into_iter().for_each(|tp| types.extend(tp));
-            streams.push(quote! {
-                pub mod #ns {
-                    use super::*;
-                    #types
-                }
-            });
+    /// Gets the type of a field before it is made optional, as far as `create_field_initializer` needs it, without creating local types.
+    /// The values of a local enum are its variants, whatever the name of the enum is.
+    fn get_field_type_name(&self, doc_file: &Path, property: &ResolvedSchema) -> Result<TokenStream> {
+        match &property.ref_key {
+            Some(ref_key) => self.get_type_name(ref_key),
+            None if is_local_enum(property) || is_local_struct(property) => Ok(quote! { Local }),
+            None => get_type_name_for_schema(self, doc_file, &property.schema.common, AsReference::False),
         }
+    }
 
-        Ok(streams)
+    /// Creates the checks for the constraints of a property, including the nested models.
+    /// The checks expect the property to be referenced by `value`.
+    fn create_property_checks(&self, doc_file: &Path, property_name: &str, property: &ResolvedSchema) -> Result<TokenStream> {
+        // the constraints of referenced schemas are checked by their own `validate`, except for arrays
+        // a resource ID is checked when it is parsed
+        let is_resource_id = self.config.resource_ids && is_arm_id(&property.schema.common);
+        let is_mapped = property.ref_key.as_ref().map_or(false, |ref_key| self.is_mapped(ref_key));
+        if is_mapped {
+            // a configured type has no `validate`
+            return Ok(TokenStream::new());
+        }
+        let is_format_type = self.get_format_type(&property.schema.common).is_some();
+        let mut checks = if (property.ref_key.is_none() || is_array(&property.schema.common)) && !is_resource_id && !is_format_type {
+            create_constraint_checks(property_name, &property.schema.common)
+        } else {
+            TokenStream::new()
+        };
+        if is_struct(property) {
+            checks.extend(quote! {
+                value.validate().map_err(|err| err.within(#property_name))?;
+            });
+        } else if is_array(&property.schema.common) {
+            if let ReferenceOr::Reference { .. } = get_schema_array_items(&property.schema.common)? {
+                let items_doc_file = property
+                    .ref_key
+                    .as_ref()
+                    .map(|ref_key| ref_key.file_path.as_path())
+                    .unwrap_or(doc_file);
+                let items = self
+                    .spec
+                    .resolve_schema(items_doc_file, get_schema_array_items(&property.schema.common)?)
+                    .context(SpecError)?;
+                let items_are_mapped = items.ref_key.as_ref().map_or(false, |ref_key| self.is_mapped(ref_key));
+                if is_struct(&items) && !items_are_mapped {
+                    checks.extend(quote! {
+                        for item in value.iter() {
+                            item.validate().map_err(|err| err.within(#property_name))?;
+                        }
+                    });
+                }
+            }
+        }
+        Ok(checks)
     }
 
-    /// Creates the type reference for a struct field from a struct property.
+    /// Creates the type reference for a struct field from a struct property, along with its type in the input model when it differs.
     /// Optionally, creates a type for a local schema.
     fn create_struct_field_type(
         &self,
         doc_file: &Path,
         namespace: &TokenStream,
+        local_type_names: &mut UniqueIdents,
         property_name: &str,
         property: &ResolvedSchema,
-    ) -> Result<(TokenStream, Vec<TokenStream>)> {
+    ) -> Result<(TokenStream, Option<TokenStream>, Vec<TokenStream>)> {
         match &property.ref_key {
-            Some(ref_key) => {
-                let tp = ident(&ref_key.name.to_camel_case()).context(IdentError {
+            Some(ref_key) => Ok((self.get_type_name(ref_key)?, self.get_input_type_name(ref_key)?, Vec::new())),
+            None if is_local_enum(property) || is_local_struct(property) => {
+                let id = ident(&get_client_name(property_name, &property.schema.common).to_camel_case()).context(IdentError {
                     file: file!(),
                     line: line!(),
                 })?;
-                Ok((tp, Vec::new()))
-            }
-            None => {
+                let id = local_type_names.insert(id).context(IdentError {
+                    file: file!(),
+                    line: line!(),
+                })?;
+                let type_name = &id.to_string();
                 if is_local_enum(property) {
-                    let (tp_name, tp) = create_enum(namespace, property_name, property)?;
-                    Ok((tp_name, vec![tp]))
-                } else if is_local_struct(property) {
-                    let id = ident(&property_name.to_camel_case()).context(IdentError {
-                        file: file!(),
-                        line: line!(),
-                    })?;
+                    let model = self.get_model_attributes(None, TypeTraits::ALL);
+                    let (tp_name, tp) = self.create_enum(namespace, type_name, property, None, &model)?;
+                    Ok((tp_name, None, vec![tp]))
+                } else {
                     let tp_name = quote! {#namespace::#id};
-                    let tps = self.create_struct(doc_file, property_name, property)?;
+                    let input_id = if self.config.input_models && self.has_local_input_model(doc_file, &property.schema)? {
+                        let input_id = ident(&format!("{}Input", type_name)).context(IdentError {
+                            file: file!(),
+                            line: line!(),
+                        })?;
+                        Some(local_type_names.insert(input_id).context(IdentError {
+                            file: file!(),
+                            line: line!(),
+                        })?)
+                    } else {
+                        None
+                    };
+                    let input_type_name = input_id.as_ref().map(ToString::to_string);
+                    let tps = self.create_struct(doc_file, None, property_name, type_name, input_type_name.as_deref(), property)?;
                     // println!("creating local struct {:?} {}", tp_name, tps.len());
-                    Ok((tp_name, tps))
-                } else {
-                    Ok((get_type_name_for_schema(&property.schema.common, AsReference::False)?, Vec::new()))
+                    Ok((tp_name, input_id.map(|input_id| quote! {#namespace::#input_id}), tps))
                 }
             }
+            None => Ok((
+                get_type_name_for_schema(self, doc_file, &property.schema.common, AsReference::False)?,
+                self.get_input_type_name_for_schema(doc_file, &property.schema.common)?,
+                Vec::new(),
+            )),
+        }
+    }
+
+    /// Gets the type in an input model for a schema or a reference, when it differs from the type of the schema.
+    fn get_input_type_name_for_schema_ref(&self, doc_file: &Path, schema: &ReferenceOr<Schema>) -> Result<Option<TokenStream>> {
+        match schema {
+            ReferenceOr::Reference { reference, .. } => {
+                self.get_input_type_name(&self.spec.schema_ref_key(doc_file, reference).context(SpecError)?)
+            }
+            ReferenceOr::Item(schema) => self.get_input_type_name_for_schema(doc_file, &schema.common),
+        }
+    }
+
+    /// Gets the type in an input model for a schema that is not a struct, which differs for a list of models with input models.
+    fn get_input_type_name_for_schema(&self, doc_file: &Path, schema: &SchemaCommon) -> Result<Option<TokenStream>> {
+        if !is_array(schema) || self.get_format_type(schema).is_some() {
+            return Ok(None);
         }
+        let items = self.get_input_type_name_for_schema_ref(doc_file, get_schema_array_items(schema)?)?;
+        Ok(items.map(|items| quote! { Vec<#items> }))
     }
 }
 
@@ -333,14 +1635,42 @@ pub enum Error {
     SpecError {
         source: spec::Error,
     },
+    ResourceIdError {
+        source: resource_id::Error,
+    },
     ArrayExpectedToHaveItems,
     NoNameForRef,
+    ParseItemsError {
+        source: syn::Error,
+    },
+    #[snafu(display("ParseDeriveError {} {}", derive, source))]
+    ParseDeriveError {
+        source: syn::Error,
+        derive: String,
+    },
+    #[snafu(display("ParseAttributeError {} {}", attribute, source))]
+    ParseAttributeError {
+        source: syn::Error,
+        attribute: String,
+    },
+    #[snafu(display("ParseTypePathError {} {}", type_path, source))]
+    ParseTypePathError {
+        source: syn::Error,
+        type_path: String,
+    },
     #[snafu(display("IdentError at {}:{} {} ", file, line, source))]
     IdentError {
         source: crate::identifier::Error,
         file: &'static str,
         line: u32,
     },
+    #[snafu(display(
+        "The blocking operations need a synchronous HTTP client, which the {} target does not take",
+        target
+    ))]
+    BlockingTargetError {
+        target: &'static str,
+    },
     #[snafu(display("CreateEnumIdentError {} {} {}", property_name, enum_value, source))]
     CreateEnumIdentError {
         #[snafu(source(from(Error, Box::new)))]
@@ -350,6 +1680,86 @@ pub enum Error {
     },
 }
 
+/// The code of a generated module, which is written either as a single file, or split into a file per part.
+pub struct ModuleCode {
+    /// The code at the top of the module, such as the header and the items that all of the parts use.
+    pub header: TokenStream,
+    pub parts: Vec<ModulePart>,
+}
+
+/// A part of a generated module, such as the models from a specification file or an operation group.
+pub struct ModulePart {
+    pub name: TokenStream,
+    /// The attributes of the module of the part, such as its documentation.
+    pub attributes: TokenStream,
+    /// A public part is a public module. The items of another part are re-exported, as if it were not there.
+    pub is_public: bool,
+    pub content: TokenStream,
+}
+
+impl ModuleCode {
+    /// Creates the code of the module as a single file.
+    pub fn to_file(&self) -> TokenStream {
+        let mut file = self.header.clone();
+        for part in &self.parts {
+            let ModulePart {
+                name, attributes, content, ..
+            } = part;
+            if part.is_public {
+                file.extend(quote! {
+                    #attributes
+                    pub mod #name {
+                        #content
+                    }
+                });
+            } else {
+                file.extend(content.clone());
+            }
+        }
+        file
+    }
+
+    /// Creates the code of the module as a `mod.rs` file, and a file for each part, by the name of the part.
+    pub fn to_files(&self) -> (TokenStream, Vec<(String, TokenStream)>) {
+        let mut mod_file = self.header.clone();
+        let mut files = Vec::new();
+        let generated_by = create_generated_by_header();
+        for part in &self.parts {
+            let ModulePart {
+                name, attributes, content, ..
+            } = part;
+            if part.is_public {
+                mod_file.extend(quote! {
+                    #attributes
+                    pub mod #name;
+                });
+                files.push((
+                    name.to_string(),
+                    quote! {
+                        #generated_by
+                        #content
+                    },
+                ));
+            } else {
+                mod_file.extend(quote! {
+                    #attributes
+                    mod #name;
+                    pub use #name::*;
+                });
+                files.push((
+                    name.to_string(),
+                    quote! {
+                        #generated_by
+                        use super::*;
+                        #content
+                    },
+                ));
+            }
+        }
+        (mod_file, files)
+    }
+}
+
 /// Whether or not to pass a type is a reference.
 #[derive(Copy, Clone)]
 pub enum AsReference {
@@ -357,120 +1767,899 @@ pub enum AsReference {
     False,
 }
 
-fn is_vec(ts: &TokenStream) -> bool {
-    ts.to_string().starts_with("Vec <")
-}
-
-fn is_array(schema: &SchemaCommon) -> bool {
-    matches!(schema.type_, Some(DataType::Array))
+/// The configured derives and attributes for a model, that have been parsed for code generation.
+#[derive(Clone, Default)]
+struct ModelAttributesCode {
+    /// The derives, along with the names that they are checked by.
+    derives: Vec<(String, TokenStream)>,
+    attributes: Vec<TokenStream>,
 }
 
-fn is_string(schema: &SchemaCommon) -> bool {
-    matches!(schema.type_, Some(DataType::String))
+/// The traits with a conditional derive, that a type allows as the types of all of its fields implement them.
+#[derive(Clone, Copy)]
+struct TypeTraits {
+    /// `Eq`, which floats do not implement
+    eq: bool,
+    /// `Ord` and `PartialOrd`, which floats and `serde_json::Value` do not implement
+    ord: bool,
+    /// `Hash`, which floats and `serde_json::Value` do not implement
+    hash: bool,
+    /// `Default`, which is implemented for a struct when its constructor parameters implement it
+    default: bool,
 }
 
-fn get_schema_array_items(schema: &SchemaCommon) -> Result<&ReferenceOr<Schema>> {
-    Ok(schema.items.as_ref().as_ref().context(ArrayExpectedToHaveItems)?)
-}
+impl TypeTraits {
+    const ALL: Self = Self {
+        eq: true,
+        ord: true,
+        hash: true,
+        default: true,
+    };
 
-pub fn create_generated_by_header() -> TokenStream {
-    let version = env!("CARGO_PKG_VERSION");
-    let comment = format!("generated by AutoRust {}", &version);
-    quote! { #![doc = #comment] }
+    fn and(self, other: Self) -> Self {
+        Self {
+            eq: self.eq && other.eq,
+            ord: self.ord && other.ord,
+            hash: self.hash && other.hash,
+            default: self.default && other.default,
+        }
+    }
 }
 
-fn is_local_enum(property: &ResolvedSchema) -> bool {
-    property.schema.common.enum_.len() > 0
+/// A struct field that has been prepared for code generation.
+#[derive(Clone)]
+struct StructFieldCode {
+    name: TokenStream,
+    type_name: TokenStream,
+    /// The type of the field in the input model, when it differs, as the type has an input model.
+    input_type_name: Option<TokenStream>,
+    doc: TokenStream,
+    deprecated: TokenStream,
+    serde: TokenStream,
+    is_read_only: bool,
+    /// Whether the field is a secret, such as a password or a key, which is redacted in `Debug` output.
+    is_secret: bool,
+    /// The value set by the constructor, or `None` if it is a constructor parameter.
+    initializer: Option<TokenStream>,
+    /// The checks run by `validate`.
+    validation: TokenStream,
+    /// The fields of an `x-ms-client-flatten` property, which are moved into the parent struct.
+    flattened: Option<FlattenedCode>,
 }
 
-fn is_local_struct(property: &ResolvedSchema) -> bool {
-    property.schema.properties.len() > 0
+/// A property with `x-ms-client-flatten` that has been prepared for code generation.
+#[derive(Clone)]
+struct FlattenedCode {
+    property_name: String,
+    /// The type of the struct that the fields are nested in when serialized.
+    type_name: TokenStream,
+    /// The input model of the struct that the fields are nested in, if it has one.
+    input_type_name: Option<TokenStream>,
+    is_required: bool,
+    fields: Vec<StructFieldCode>,
 }
 
-fn create_enum(namespace: &TokenStream, property_name: &str, property: &ResolvedSchema) -> Result<(TokenStream, TokenStream)> {
-    let enum_values = enum_values_as_strings(&property.schema.common.enum_);
-    let id = ident(&property_name.to_camel_case()).context(IdentError {
-        file: file!(),
-        line: line!(),
-    })?;
-    let mut values = TokenStream::new();
-    for name in enum_values {
-        let nm = name
-            .to_camel_case_ident()
-            .context(IdentError {
-                file: file!(),
-                line: line!(),
-            })
-            .context(CreateEnumIdentError {
-                property_name: property_name.to_owned(),
-                enum_value: name.to_owned(),
-            })?;
-        let rename = if &nm.to_string() == name {
-            quote! {}
-        } else {
-            quote! { #[serde(rename = #name)] }
-        };
-        let value = quote! {
-            #rename
-            #nm,
-        };
-        values.extend(value);
+impl StructFieldCode {
+    /// Gets the field as it is in an input model.
+    fn to_input(&self) -> Self {
+        let mut field = self.clone();
+        if let Some(input_type_name) = &self.input_type_name {
+            field.type_name = input_type_name.clone();
+        }
+        field
     }
-    let nm = ident(&property_name.to_camel_case()).context(IdentError {
-        file: file!(),
-        line: line!(),
-    })?;
-    let tp = quote! {
-        #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-        pub enum #nm {
-            #values
+
+    fn declaration(&self) -> TokenStream {
+        let StructFieldCode {
+            name,
+            type_name,
+            doc,
+            deprecated,
+            serde,
+            ..
+        } = self;
+        quote! {
+            #doc
+            #deprecated
+            #serde
+            pub #name: #type_name,
         }
-    };
-    let tp_name = quote! {#namespace::#id};
-    Ok((tp_name, tp))
+    }
 }
 
-/// Wraps a type in an Option if is not required.
-fn require(is_required: bool, tp: TokenStream) -> TokenStream {
-    if is_required {
-        tp
+/// Derives the traits of a struct.
+/// `Debug` is implemented by `create_struct_debug` instead when there are secret fields.
+fn create_struct_derive(fields: &[&StructFieldCode], model: &ModelAttributesCode) -> TokenStream {
+    let derives = model.derives.iter().map(|(_, derive)| derive);
+    let attributes = &model.attributes;
+    if fields.iter().any(|field| field.is_secret) {
+        quote! {
+            #[derive(Clone, PartialEq, Serialize, Deserialize #(, #derives)*)]
+            #(#[#attributes])*
+        }
     } else {
-        quote! { Option<#tp> }
+        quote! {
+            #[derive(Clone, Debug, PartialEq, Serialize, Deserialize #(, #derives)*)]
+            #(#[#attributes])*
+        }
     }
 }
 
-fn enum_values_as_strings(values: &Vec<Value>) -> Vec<&str> {
-    values
-        .iter()
-        .filter_map(|v| match v {
-            Value::String(s) => Some(s.as_str()),
-            _ => None,
-        })
-        .collect()
+/// Implements `Debug` for a struct with secret fields, which are redacted so that they do not end up in logs.
+fn create_struct_debug(struct_name: &TokenStream, fields: &[&StructFieldCode]) -> TokenStream {
+    if !fields.iter().any(|field| field.is_secret) {
+        return TokenStream::new();
+    }
+    let struct_name_str = struct_name.to_string();
+    let debug_fields = fields.iter().map(|field| {
+        let name = &field.name;
+        let name_str = name.to_string();
+        let name_str = name_str.trim_start_matches("r#");
+        if !field.is_secret {
+            quote! { .field(#name_str, &self.#name) }
+        } else if is_option(&field.type_name) {
+            quote! { .field(#name_str, &self.#name.as_ref().map(|_| "<redacted>")) }
+        } else {
+            quote! { .field(#name_str, &"<redacted>") }
+        }
+    });
+    quote! {
+        impl std::fmt::Debug for #struct_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(#struct_name_str)
+                    #(#debug_fields)*
+                    .finish()
+            }
+        }
+    }
 }
 
-fn get_param_type(param: &Parameter) -> Result<TokenStream> {
-    let is_required = param.required.unwrap_or(false);
-    let is_array = is_array(&param.common);
-    let tp = if let Some(_param_type) = &param.common.type_ {
-        get_type_name_for_schema(&param.common, AsReference::True)?
-    } else if let Some(schema) = &param.schema {
-        get_type_name_for_schema_ref(schema, AsReference::True)?
+/// Creates a `new` constructor that takes the required fields,
+/// and implements `Default` when there are none.
+fn create_struct_constructor(struct_name: &TokenStream, fields: &[&StructFieldCode], is_default: bool) -> TokenStream {
+    let mut params = Vec::new();
+    let mut inits = Vec::new();
+    for field in fields {
+        let name = &field.name;
+        match &field.initializer {
+            Some(value) => inits.push(quote! { #name: #value }),
+            None => {
+                let type_name = &field.type_name;
+                params.push(quote! { #name: #type_name });
+                inits.push(quote! { #name });
+            }
+        }
+    }
+    let default = if params.len() == 0 {
+        quote! {
+            impl Default for #struct_name {
+                fn default() -> Self {
+                    Self::new()
+                }
+            }
+        }
+    } else if is_default {
+        let defaults = params.iter().map(|_| quote! { Default::default() });
+        quote! {
+            impl Default for #struct_name {
+                fn default() -> Self {
+                    Self::new(#(#defaults),*)
+                }
+            }
+        }
     } else {
-        eprintln!("WARN unkown param type for {}", &param.name);
-        quote! { &serde_json::Value }
+        quote! {}
     };
-    Ok(require(is_required || is_array, tp))
-}
-
-fn get_param_name(param: &Parameter) -> Result<TokenStream> {
-    ident(&param.name.to_snake_case()).context(IdentError {
-        file: file!(),
-        line: line!(),
-    })
+    quote! {
+        impl #struct_name {
+            pub fn new(#(#params),*) -> Self {
+                Self {
+                    #(#inits),*
+                }
+            }
+        }
+        #default
+    }
 }
 
-fn parse_params(param_re: &Regex, path: &str) -> Vec<String> {
+/// Replaces the `x-ms-client-flatten` fields with the fields that they contain.
+/// When the flattened property is optional, so are its fields.
+fn flatten_fields(fields: &[StructFieldCode]) -> Vec<StructFieldCode> {
+    let mut flat_fields = Vec::new();
+    for field in fields {
+        let nested_fields = match &field.flattened {
+            Some(flattened) => flattened.fields.iter().map(|nested| (nested, flattened.is_required)).collect(),
+            None => vec![(field, true)],
+        };
+        for (nested, is_required) in nested_fields {
+            let mut flat_field = nested.clone();
+            flat_field.is_read_only = field.is_read_only || nested.is_read_only;
+            if !is_required && !is_vec(&nested.type_name) && !is_option(&nested.type_name) {
+                let type_name = &nested.type_name;
+                flat_field.type_name = quote! { Option<#type_name> };
+                flat_field.initializer = Some(match &nested.initializer {
+                    Some(value) => quote! { Some(#value) },
+                    None => quote! { None },
+                });
+            }
+            // the struct is serialized and validated in its nested shape
+            flat_field.serde = quote! {};
+            flat_field.validation = quote! {};
+            flat_field.flattened = None;
+            flat_fields.push(flat_field);
+        }
+    }
+    flat_fields
+}
+
+/// Creates the conversions between a struct with flattened fields and its local wire struct, which has the nested shape.
+fn create_struct_conversions(
+    struct_name: &TokenStream,
+    wire_name: &TokenStream,
+    fields: &[StructFieldCode],
+    flat_fields: &[StructFieldCode],
+) -> TokenStream {
+    let names: Vec<_> = fields.iter().map(|field| &field.name).collect();
+    let flat_names: Vec<_> = flat_fields.iter().map(|field| &field.name).collect();
+    let mut from_wire = TokenStream::new();
+    let mut into_wire = TokenStream::new();
+    for field in fields {
+        let flattened = match &field.flattened {
+            Some(flattened) => flattened,
+            None => continue,
+        };
+        let name = &field.name;
+        let type_name = &flattened.type_name;
+        let nested_names: Vec<_> = flattened.fields.iter().map(|field| &field.name).collect();
+        if flattened.is_required {
+            from_wire.extend(quote! {
+                let (#(#nested_names,)*) = (#(#name.#nested_names,)*);
+            });
+            into_wire.extend(quote! {
+                let #name = #type_name { #(#nested_names),* };
+            });
+        } else {
+            let mut some_values = Vec::new();
+            let mut none_values = Vec::new();
+            let mut wrapped_names = Vec::new();
+            let mut is_set = Vec::new();
+            for nested in &flattened.fields {
+                let nested_name = &nested.name;
+                if is_vec(&nested.type_name) {
+                    some_values.push(quote! { #name.#nested_name });
+                    none_values.push(quote! { Vec::new() });
+                    is_set.push(quote! { !#nested_name.is_empty() });
+                } else if is_option(&nested.type_name) {
+                    some_values.push(quote! { #name.#nested_name });
+                    none_values.push(quote! { None });
+                    is_set.push(quote! { #nested_name.is_some() });
+                } else {
+                    some_values.push(quote! { Some(#name.#nested_name) });
+                    none_values.push(quote! { None });
+                    wrapped_names.push(nested_name);
+                }
+            }
+            from_wire.extend(quote! {
+                let (#(#nested_names,)*) = match #name {
+                    Some(#name) => (#(#some_values,)*),
+                    None => (#(#none_values,)*),
+                };
+            });
+            // the nested struct is only sent when its required fields are set
+            if wrapped_names.len() > 0 {
+                into_wire.extend(quote! {
+                    let #name = match (#(#wrapped_names,)*) {
+                        (#(Some(#wrapped_names),)*) => Some(#type_name { #(#nested_names),* }),
+                        _ => None,
+                    };
+                });
+            } else {
+                into_wire.extend(quote! {
+                    let #name = if #(#is_set)||* {
+                        Some(#type_name { #(#nested_names),* })
+                    } else {
+                        None
+                    };
+                });
+            }
+        }
+    }
+    quote! {
+        impl From<#wire_name> for #struct_name {
+            fn from(wire: #wire_name) -> Self {
+                let #wire_name { #(#names),* } = wire;
+                #from_wire
+                Self { #(#flat_names),* }
+            }
+        }
+        impl From<#struct_name> for #wire_name {
+            fn from(value: #struct_name) -> Self {
+                let #struct_name { #(#flat_names),* } = value;
+                #into_wire
+                Self { #(#names),* }
+            }
+        }
+    }
+}
+
+/// Creates a `validate` method that checks the schema constraints of the fields.
+fn create_struct_validate(struct_name: &TokenStream, fields: &[&StructFieldCode]) -> TokenStream {
+    let checks = fields.iter().map(|field| &field.validation);
+    quote! {
+        impl #struct_name {
+            pub fn validate(&self) -> std::result::Result<(), model_validation::ValidationError> {
+                #(#checks)*
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Creates the checks for the `minLength`, `maxLength`, `pattern`, `minimum`, `maximum`,
+/// `minItems`, `maxItems` and `uniqueItems` constraints of a schema.
+/// The checks expect the value to be referenced by `value`.
+fn create_constraint_checks(field: &str, schema: &SchemaCommon) -> TokenStream {
+    let option_u64 = |value: Option<u64>| match value {
+        Some(value) => quote! { Some(#value) },
+        None => quote! { None },
+    };
+    let option_f64 = |value: Option<f64>| match value {
+        Some(value) => quote! { Some(#value) },
+        None => quote! { None },
+    };
+    let mut checks = TokenStream::new();
+    match schema.type_ {
+        Some(DataType::String) if schema.enum_.len() == 0 => {
+            if schema.min_length.is_some() || schema.max_length.is_some() {
+                let min = option_u64(schema.min_length);
+                let max = option_u64(schema.max_length);
+                checks.extend(quote! { model_validation::length(#field, value, #min, #max)?; });
+            }
+            if let Some(pattern) = &schema.pattern {
+                checks.extend(quote! {
+                    {
+                        lazy_static::lazy_static! {
+                            static ref PATTERN: Option<regex::Regex> = regex::Regex::new(#pattern).ok();
+                        }
+                        model_validation::pattern(#field, value, #pattern, PATTERN.as_ref())?;
+                    }
+                });
+            }
+        }
+        Some(DataType::Integer) | Some(DataType::Number) if schema.enum_.len() == 0 => {
+            if schema.minimum.is_some() || schema.maximum.is_some() {
+                let min = option_f64(schema.minimum);
+                let exclusive_min = schema.exclusive_minimum == Some(true);
+                let max = option_f64(schema.maximum);
+                let exclusive_max = schema.exclusive_maximum == Some(true);
+                checks.extend(quote! { model_validation::range(#field, *value as f64, #min, #exclusive_min, #max, #exclusive_max)?; });
+            }
+        }
+        Some(DataType::Array) => {
+            let unique = schema.unique_items == Some(true);
+            if schema.min_items.is_some() || schema.max_items.is_some() || unique {
+                let min = option_u64(schema.min_items);
+                let max = option_u64(schema.max_items);
+                checks.extend(quote! { model_validation::items(#field, value, #min, #max, #unique)?; });
+            }
+        }
+        _ => {}
+    }
+    checks
+}
+
+/// Creates the `model_validation` module with the error type and the checks used by the `validate` methods and operations.
+fn create_validation_module() -> TokenStream {
+    quote! {
+        pub mod model_validation {
+            #[derive(Clone, Debug, PartialEq)]
+            pub struct ValidationError {
+                /// The path to the field, using the names from the specification.
+                pub field: String,
+                pub rule: Rule,
+            }
+
+            /// The schema constraint that a value did not satisfy.
+            #[derive(Clone, Debug, PartialEq)]
+            pub enum Rule {
+                MinLength(u64),
+                MaxLength(u64),
+                Pattern(String),
+                Minimum(f64),
+                ExclusiveMinimum(f64),
+                Maximum(f64),
+                ExclusiveMaximum(f64),
+                MinItems(u64),
+                MaxItems(u64),
+                UniqueItems,
+            }
+
+            impl ValidationError {
+                fn new(field: &str, rule: Rule) -> Self {
+                    Self {
+                        field: field.to_owned(),
+                        rule,
+                    }
+                }
+
+                /// Prefixes the field with the name of the parent field.
+                pub fn within(mut self, parent: &str) -> Self {
+                    self.field = format!("{}.{}", parent, self.field);
+                    self
+                }
+            }
+
+            impl std::fmt::Display for ValidationError {
+                fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    write!(f, "{} does not satisfy {:?}", self.field, self.rule)
+                }
+            }
+
+            impl std::error::Error for ValidationError {}
+
+            pub fn length(field: &str, value: &str, min: Option<u64>, max: Option<u64>) -> Result<(), ValidationError> {
+                let len = value.chars().count() as u64;
+                match (min, max) {
+                    (Some(min), _) if len < min => Err(ValidationError::new(field, Rule::MinLength(min))),
+                    (_, Some(max)) if len > max => Err(ValidationError::new(field, Rule::MaxLength(max))),
+                    _ => Ok(()),
+                }
+            }
+
+            /// The regular expression of a pattern is compiled once, where it is checked.
+            /// It is `None` for patterns that are not supported by the `regex` crate, which are not checked.
+            pub fn pattern(field: &str, value: &str, pattern: &str, re: Option<&regex::Regex>) -> Result<(), ValidationError> {
+                match re {
+                    Some(re) if !re.is_match(value) => Err(ValidationError::new(field, Rule::Pattern(pattern.to_owned()))),
+                    _ => Ok(()),
+                }
+            }
+
+            pub fn range(
+                field: &str,
+                value: f64,
+                min: Option<f64>,
+                exclusive_min: bool,
+                max: Option<f64>,
+                exclusive_max: bool,
+            ) -> Result<(), ValidationError> {
+                if let Some(min) = min {
+                    if exclusive_min && value <= min {
+                        return Err(ValidationError::new(field, Rule::ExclusiveMinimum(min)));
+                    } else if value < min {
+                        return Err(ValidationError::new(field, Rule::Minimum(min)));
+                    }
+                }
+                if let Some(max) = max {
+                    if exclusive_max && value >= max {
+                        return Err(ValidationError::new(field, Rule::ExclusiveMaximum(max)));
+                    } else if value > max {
+                        return Err(ValidationError::new(field, Rule::Maximum(max)));
+                    }
+                }
+                Ok(())
+            }
+
+            pub fn items<T: PartialEq>(field: &str, value: &[T], min: Option<u64>, max: Option<u64>, unique: bool) -> Result<(), ValidationError> {
+                let len = value.len() as u64;
+                if let Some(min) = min {
+                    if len < min {
+                        return Err(ValidationError::new(field, Rule::MinItems(min)));
+                    }
+                }
+                if let Some(max) = max {
+                    if len > max {
+                        return Err(ValidationError::new(field, Rule::MaxItems(max)));
+                    }
+                }
+                if unique && value.iter().enumerate().any(|(i, item)| value[..i].contains(item)) {
+                    return Err(ValidationError::new(field, Rule::UniqueItems));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Creates an expression for the `x-ms-client-default` or `default` value of a property.
+fn create_default_value(property: &ResolvedSchema, type_name: &TokenStream) -> Result<Option<TokenStream>> {
+    let common = &property.schema.common;
+    match common.x_ms_client_default.as_ref().or(common.default.as_ref()) {
+        Some(value) => create_value(property, type_name, value),
+        None => Ok(None),
+    }
+}
+
+/// Creates an expression for the value of a constant property, see `is_constant`.
+fn create_constant_value(property: &ResolvedSchema, type_name: &TokenStream) -> Result<Option<TokenStream>> {
+    let common = &property.schema.common;
+    if is_constant(common) {
+        create_value(property, type_name, &common.enum_[0])
+    } else {
+        Ok(None)
+    }
+}
+
+/// Creates an expression for a value of a property.
+/// Only values for strings, numbers, booleans and enums are supported.
+fn create_value(property: &ResolvedSchema, type_name: &TokenStream, value: &Value) -> Result<Option<TokenStream>> {
+    let tp = if is_local_enum(property) {
+        match get_enum_type(&property.schema) {
+            EnumType::Alias(alias) => alias.to_string(),
+            _ => {
+                let variant = get_enum_variant_name(&property.schema, value)?;
+                return Ok(Some(quote! { #type_name::#variant }));
+            }
+        }
+    } else {
+        type_name.to_string()
+    };
+    let value = match value {
+        Value::String(value) if tp == "String" => Some(quote! { #value.to_owned() }),
+        Value::Bool(value) if tp == "bool" => Some(quote! { #value }),
+        // a number that does not fit in the type of the field is left out, as the literal would not compile
+        Value::Number(number) => match tp.as_str() {
+            "i32" => number
+                .as_i64()
+                .and_then(|n| i32::try_from(n).ok())
+                .map(|n| Literal::i32_unsuffixed(n).into_token_stream()),
+            "i64" => number.as_i64().map(|n| Literal::i64_unsuffixed(n).into_token_stream()),
+            "f32" => number
+                .as_f64()
+                .map(|n| n as f32)
+                .filter(|n| n.is_finite())
+                .map(|n| Literal::f32_unsuffixed(n).into_token_stream()),
+            "f64" => number.as_f64().map(|n| Literal::f64_unsuffixed(n).into_token_stream()),
+            _ => None,
+        },
+        _ => None,
+    };
+    Ok(value)
+}
+
+/// Whether the schema is an `enum` with a single value, which is a constant from the client's point of view.
+/// An `x-ms-enum` with `modelAsString` may be extended with other values by the service, so it is not a constant.
+fn is_constant(schema: &SchemaCommon) -> bool {
+    let model_as_string = schema.x_ms_enum.as_ref().and_then(|x_ms_enum| x_ms_enum.model_as_string) == Some(true);
+    schema.enum_.len() == 1 && !model_as_string
+}
+
+/// Gets the name to use in Rust for a schema, preferring its `x-ms-client-name`.
+fn get_client_name<'a>(name: &'a str, schema: &'a SchemaCommon) -> &'a str {
+    schema.x_ms_client_name.as_deref().unwrap_or(name)
+}
+
+/// Whether a property is only ever set by the service,
+/// either `readOnly` or with an `x-ms-mutability` that does not allow create or update.
+fn is_read_only(schema: &Schema) -> bool {
+    if schema.read_only == Some(true) {
+        return true;
+    }
+    let mutability = &schema.x_ms_mutability;
+    mutability.len() > 0 && !mutability.iter().any(|m| matches!(m, MsMutability::Create | MsMutability::Update))
+}
+
+/// Whether a property is marked with `x-ms-secret` or is a password.
+fn is_secret(schema: &Schema) -> bool {
+    schema.x_ms_secret == Some(true) || schema.common.format.as_deref() == Some("password")
+}
+
+fn is_vec(ts: &TokenStream) -> bool {
+    ts.to_string().starts_with("Vec <")
+}
+
+fn is_option(ts: &TokenStream) -> bool {
+    ts.to_string().starts_with("Option <")
+}
+
+/// Whether a property has `x-ms-client-flatten`, which may be next to a `$ref`.
+fn is_client_flatten(property: &ReferenceOr<Schema>) -> bool {
+    let flatten = match property {
+        ReferenceOr::Reference { x_ms_client_flatten, .. } => x_ms_client_flatten,
+        ReferenceOr::Item(schema) => &schema.x_ms_client_flatten,
+    };
+    flatten == &Some(true)
+}
+
+fn is_array(schema: &SchemaCommon) -> bool {
+    matches!(schema.type_, Some(DataType::Array))
+}
+
+/// Whether a string is the ID of an ARM resource.
+fn is_arm_id(schema: &SchemaCommon) -> bool {
+    schema.format.as_deref() == Some("arm-id") || schema.x_ms_arm_id_details.is_some()
+}
+
+fn is_string(schema: &SchemaCommon) -> bool {
+    matches!(schema.type_, Some(DataType::String))
+}
+
+fn get_schema_array_items(schema: &SchemaCommon) -> Result<&ReferenceOr<Schema>> {
+    Ok(schema.items.as_ref().as_ref().context(ArrayExpectedToHaveItems)?)
+}
+
+pub fn create_generated_by_header() -> TokenStream {
+    let version = env!("CARGO_PKG_VERSION");
+    let comment = format!(" generated by AutoRust {}", &version);
+    quote! { #![doc = #comment] }
+}
+
+/// Creates the documentation for a schema from its description, or its title if it has none.
+fn create_schema_doc(schema: &SchemaCommon) -> TokenStream {
+    create_doc(schema.description.as_deref().or(schema.title.as_deref()).unwrap_or_default())
+}
+
+/// Gets the description of a property, where the description next to a `$ref` overrides the one of the referenced schema.
+fn get_property_description<'a>(property: &'a ReferenceOr<Schema>, schema: &'a SchemaCommon) -> Option<&'a str> {
+    match property {
+        ReferenceOr::Reference {
+            description: Some(description),
+            ..
+        } => Some(description),
+        _ => schema.description.as_deref().or(schema.title.as_deref()),
+    }
+}
+
+fn is_local_enum(property: &ResolvedSchema) -> bool {
+    property.schema.common.enum_.len() > 0
+}
+
+fn is_local_struct(property: &ResolvedSchema) -> bool {
+    property.schema.properties.len() > 0
+}
+
+/// Whether a struct is created for the schema, which has a `validate` method.
+fn is_struct(schema: &ResolvedSchema) -> bool {
+    if is_array(&schema.schema.common) || is_local_enum(schema) {
+        false
+    } else {
+        schema.ref_key.is_some() || is_local_struct(schema)
+    }
+}
+
+fn create_enum(
+    namespace: &TokenStream,
+    property_name: &str,
+    property: &ResolvedSchema,
+    deprecation: Option<&Deprecation>,
+    model: &ModelAttributesCode,
+) -> Result<(TokenStream, TokenStream)> {
+    let enum_values = &property.schema.common.enum_;
+    let id = ident(&property_name.to_camel_case()).context(IdentError {
+        file: file!(),
+        line: line!(),
+    })?;
+    let tp_name = quote! {#namespace::#id};
+    let enum_type = get_enum_type(&property.schema);
+    let doc = create_schema_doc(&property.schema.common);
+    let deprecated = create_schema_deprecated(deprecation, property.schema.common.description.as_deref());
+    if let EnumType::Alias(alias) = enum_type {
+        return Ok((
+            tp_name,
+            quote! {
+                #doc
+                #deprecated
+                pub type #id = #alias;
+            },
+        ));
+    }
+    let names = get_enum_variant_names(&property.schema).context(CreateEnumIdentError {
+        property_name: property_name.to_owned(),
+        enum_value: Value::Array(enum_values.clone()).to_string(),
+    })?;
+    let mut values = TokenStream::new();
+    for (i, (value, nm)) in enum_values.iter().zip(names).enumerate() {
+        // a repeated value has the variant of its first occurrence
+        if enum_values[..i].contains(value) {
+            continue;
+        }
+        let description = get_enum_value_description(&property.schema, value);
+        let doc = create_doc(description.unwrap_or_default());
+        let deprecated = create_schema_deprecated(None, description);
+        let variant = match (&enum_type, value) {
+            (EnumType::Integer, Value::Number(number)) => {
+                let discriminant = Literal::i64_unsuffixed(number.as_i64().unwrap_or_default());
+                quote! {
+                    #doc
+                    #deprecated
+                    #nm = #discriminant,
+                }
+            }
+            (_, Value::String(name)) if &nm.to_string() != name => quote! {
+                #doc
+                #deprecated
+                #[serde(rename = #name)]
+                #nm,
+            },
+            _ => quote! {
+                #doc
+                #deprecated
+                #nm,
+            },
+        };
+        values.extend(variant);
+    }
+    let derives: Vec<_> = model.derives.iter().map(|(_, derive)| derive).collect();
+    let attributes = &model.attributes;
+    let tp = match enum_type {
+        EnumType::Integer => {
+            let repr = if property.schema.common.format.as_deref() == Some("int32") {
+                quote! { i32 }
+            } else {
+                quote! { i64 }
+            };
+            quote! {
+                #doc
+                #deprecated
+                #[derive(Clone, Debug, PartialEq, serde_repr::Serialize_repr, serde_repr::Deserialize_repr #(, #derives)*)]
+                #(#[#attributes])*
+                #[repr(#repr)]
+                pub enum #id {
+                    #values
+                }
+            }
+        }
+        _ => quote! {
+            #doc
+            #deprecated
+            #[derive(Clone, Debug, PartialEq, Serialize, Deserialize #(, #derives)*)]
+            #(#[#attributes])*
+            pub enum #id {
+                #values
+            }
+        },
+    };
+    // an enum with a single value is a constant
+    let default = if enum_values.len() == 1 {
+        let nm = get_enum_variant_name(&property.schema, &enum_values[0])?;
+        quote! {
+            impl Default for #id {
+                fn default() -> Self {
+                    Self::#nm
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+    Ok((tp_name, quote! { #tp #default }))
+}
+
+/// How the values of an `enum` are represented in Rust.
+enum EnumType {
+    /// An enum that is serialized by the names of its variants.
+    String,
+    /// An enum that is serialized by its integer discriminants.
+    Integer,
+    /// Enums of other values, such as booleans, floats or mixed types, are an alias to the type of the values.
+    Alias(TokenStream),
+}
+
+fn get_enum_type(schema: &Schema) -> EnumType {
+    let values = &schema.common.enum_;
+    if values.iter().all(Value::is_string) {
+        EnumType::String
+    } else if values.iter().all(Value::is_i64) {
+        EnumType::Integer
+    } else if values.iter().all(Value::is_number) {
+        EnumType::Alias(quote! { f64 })
+    } else if values.iter().all(Value::is_boolean) {
+        EnumType::Alias(quote! { bool })
+    } else {
+        EnumType::Alias(quote! { serde_json::Value })
+    }
+}
+
+/// Gets the name of the enum variant for a value.
+fn get_enum_variant_name(schema: &Schema, value: &Value) -> Result<TokenStream> {
+    match schema.common.enum_.iter().position(|enum_value| enum_value == value) {
+        Some(i) => Ok(get_enum_variant_names(schema)?.swap_remove(i)),
+        None => get_enum_value_name(schema, value),
+    }
+}
+
+/// Gets the names of the enum variants for all the values.
+/// Values that would get the same name are disambiguated by appending a number.
+fn get_enum_variant_names(schema: &Schema) -> Result<Vec<TokenStream>> {
+    let mut names = UniqueIdents::default();
+    let mut variants = Vec::new();
+    for value in &schema.common.enum_ {
+        let name = names.insert(get_enum_value_name(schema, value)?).context(IdentError {
+            file: file!(),
+            line: line!(),
+        })?;
+        variants.push(name);
+    }
+    Ok(variants)
+}
+
+/// Gets the `x-ms-enum` description of an enum value.
+fn get_enum_value_description<'a>(schema: &'a Schema, value: &Value) -> Option<&'a str> {
+    schema
+        .common
+        .x_ms_enum
+        .as_ref()
+        .and_then(|x_ms_enum| x_ms_enum.values.iter().find(|enum_value| &enum_value.value == value))
+        .and_then(|enum_value| enum_value.description.as_deref())
+}
+
+/// Gets the name for an enum value, using the `x-ms-enum` name if there is one.
+fn get_enum_value_name(schema: &Schema, value: &Value) -> Result<TokenStream> {
+    let x_ms_enum_name = schema
+        .common
+        .x_ms_enum
+        .as_ref()
+        .and_then(|x_ms_enum| x_ms_enum.values.iter().find(|enum_value| &enum_value.value == value))
+        .and_then(|enum_value| enum_value.name.clone());
+    let name = match (x_ms_enum_name, value) {
+        (Some(name), _) => name,
+        (None, Value::String(value)) => value.clone(),
+        (None, Value::Number(number)) => {
+            let number = number.to_string();
+            match number.strip_prefix('-') {
+                Some(number) => format!("Minus{}", number),
+                None => number,
+            }
+        }
+        (None, value) => value.to_string(),
+    };
+    name.as_str().to_camel_case_ident().context(IdentError {
+        file: file!(),
+        line: line!(),
+    })
+}
+
+/// Wraps a type in an Option if is not required.
+fn require(is_required: bool, tp: TokenStream) -> TokenStream {
+    if is_required {
+        tp
+    } else {
+        quote! { Option<#tp> }
+    }
+}
+
+fn get_param_type(cg: &CodeGen, doc_file: &Path, param: &Parameter) -> Result<TokenStream> {
+    let is_required = param.required.unwrap_or(false);
+    let is_array = is_array(&param.common);
+    let tp = if let Some(_param_type) = &param.common.type_ {
+        get_type_name_for_schema(cg, doc_file, &param.common, AsReference::True)?
+    } else if let Some(schema) = &param.schema {
+        // a body is sent to the service, so it has the input model of its type when there is one
+        match cg.get_input_type_name_for_schema_ref(doc_file, schema)? {
+            Some(input_tp) if matches!(param.in_, ParameterType::Body) => quote! { &#input_tp },
+            _ => get_type_name_for_schema_ref(cg, doc_file, schema, AsReference::True)?,
+        }
+    } else {
+        eprintln!("WARN unkown param type for {}", &param.name);
+        quote! { &serde_json::Value }
+    };
+    Ok(require(is_required || is_array, tp))
+}
+
+/// Whether a parameter has a single allowed value, which is sent without being passed in by the caller.
+fn is_constant_parameter(param: &Parameter) -> bool {
+    param.required.unwrap_or(false)
+        && matches!(param.in_, ParameterType::Path | ParameterType::Query | ParameterType::Header)
+        && is_constant(&param.common)
+}
+
+fn get_constant_parameter_value(param: &Parameter) -> String {
+    match &param.common.enum_[0] {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Gets the name of the function argument for a parameter, preferring its `x-ms-client-name`.
+fn get_param_name(param: &Parameter) -> Result<TokenStream> {
+    let name = param.x_ms_client_name.as_ref().unwrap_or(&param.name);
+    ident(&name.to_snake_case()).context(IdentError {
+        file: file!(),
+        line: line!(),
+    })
+}
+
+fn parse_params(param_re: &Regex, path: &str) -> Vec<String> {
     // capture 0 is the whole match and 1 is the actual capture like other languages
     param_re.captures_iter(path).into_iter().map(|c| c[1].to_string()).collect()
 }
@@ -479,11 +2668,11 @@ fn format_path(param_re: &Regex, path: &str) -> String {
     param_re.replace_all(path, "{}").to_string()
 }
 
-fn create_function_params(_cg: &CodeGen, _doc_file: &Path, parameters: &Vec<Parameter>) -> Result<TokenStream> {
+fn create_function_params(cg: &CodeGen, doc_file: &Path, parameters: &Vec<Parameter>) -> Result<TokenStream> {
     let mut params: Vec<TokenStream> = Vec::new();
     for param in parameters {
         let name = get_param_name(param)?;
-        let tp = get_param_type(param)?;
+        let tp = get_param_type(cg, doc_file, param)?;
         params.push(quote! { #name: #tp });
     }
     let slf = quote! { operation_config: &crate::OperationConfig };
@@ -491,13 +2680,19 @@ fn create_function_params(_cg: &CodeGen, _doc_file: &Path, parameters: &Vec<Para
     Ok(quote! { #(#params),* })
 }
 
-fn get_type_name_for_schema(schema: &SchemaCommon, as_ref: AsReference) -> Result<TokenStream> {
+fn get_type_name_for_schema(cg: &CodeGen, doc_file: &Path, schema: &SchemaCommon, as_ref: AsReference) -> Result<TokenStream> {
+    if let Some(type_path) = cg.get_format_type(schema) {
+        return match as_ref {
+            AsReference::True => Ok(quote! { &#type_path }),
+            AsReference::False => Ok(type_path.clone()),
+        };
+    }
     if let Some(schema_type) = &schema.type_ {
         let format = schema.format.as_deref();
         let ts = match schema_type {
             DataType::Array => {
                 let items = get_schema_array_items(&schema)?;
-                let vec_items_typ = get_type_name_for_schema_ref(&items, as_ref)?;
+                let vec_items_typ = get_type_name_for_schema_ref(cg, doc_file, &items, as_ref)?;
                 match as_ref {
                     AsReference::True => quote! { &Vec<#vec_items_typ> },
                     AsReference::False => quote! { Vec<#vec_items_typ> },
@@ -519,6 +2714,7 @@ fn get_type_name_for_schema(schema: &SchemaCommon, as_ref: AsReference) -> Resul
             }
             DataType::String => match as_ref {
                 AsReference::True => quote! { &str },
+                AsReference::False if cg.config.resource_ids && is_arm_id(schema) => cg.get_resource_id_type(schema),
                 AsReference::False => quote! { String },
             },
             DataType::Boolean => quote! { bool },
@@ -541,26 +2737,81 @@ fn get_type_name_for_schema(schema: &SchemaCommon, as_ref: AsReference) -> Resul
     }
 }
 
-fn get_type_name_for_schema_ref(schema: &ReferenceOr<Schema>, as_ref: AsReference) -> Result<TokenStream> {
-    match schema {
-        ReferenceOr::Reference { reference, .. } => {
-            let name = &reference.name.as_ref().context(NoNameForRef)?;
-            let idt = ident(&name.to_camel_case()).context(IdentError {
-                file: file!(),
-                line: line!(),
-            })?;
-            match as_ref {
-                AsReference::True => Ok(quote! { &#idt }),
-                AsReference::False => Ok(quote! { #idt }),
-            }
-        }
-        ReferenceOr::Item(schema) => get_type_name_for_schema(&schema.common, as_ref),
+/// Gets the name of a `type`, as it is written in a spec.
+fn get_data_type_name(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::String => "string",
+        DataType::Number => "number",
+        DataType::Integer => "integer",
+        DataType::Boolean => "boolean",
+        DataType::Array => "array",
+        DataType::Object => "object",
+        DataType::File => "file",
     }
 }
 
-fn create_response_type(rsp: &Response) -> Result<Option<TokenStream>> {
-    if let Some(schema) = &rsp.schema {
-        Ok(Some(get_type_name_for_schema_ref(schema, AsReference::False)?))
+/// Parses the Rust path of a configured type.
+fn parse_type_path(type_path: &str) -> Result<TokenStream> {
+    let tp = syn::parse_str::<syn::Type>(type_path).context(ParseTypePathError { type_path })?;
+    Ok(tp.into_token_stream())
+}
+
+/// Adds an attribute, such as a `cfg`, to each of the items.
+fn add_item_attribute(items: TokenStream, attribute: &TokenStream) -> Result<TokenStream> {
+    let mut file = syn::parse2::<syn::File>(items).context(ParseItemsError)?;
+    let attribute = syn::parse::Parser::parse2(syn::Attribute::parse_outer, attribute.clone()).context(ParseItemsError)?;
+    for item in &mut file.items {
+        let attrs = match item {
+            syn::Item::Const(item) => &mut item.attrs,
+            syn::Item::Enum(item) => &mut item.attrs,
+            syn::Item::Fn(item) => &mut item.attrs,
+            syn::Item::Impl(item) => &mut item.attrs,
+            syn::Item::Mod(item) => &mut item.attrs,
+            syn::Item::Static(item) => &mut item.attrs,
+            syn::Item::Struct(item) => &mut item.attrs,
+            syn::Item::Trait(item) => &mut item.attrs,
+            syn::Item::Type(item) => &mut item.attrs,
+            syn::Item::Use(item) => &mut item.attrs,
+            _ => continue,
+        };
+        attrs.splice(0..0, attribute.iter().cloned());
+    }
+    Ok(file.into_token_stream())
+}
+
+/// Parses the configured derives and attributes for models.
+fn parse_model_attributes(model_attributes: &ModelAttributes) -> Result<ModelAttributesCode> {
+    let mut model = ModelAttributesCode::default();
+    for derive in &model_attributes.derives {
+        let path = syn::parse_str::<syn::Path>(derive).context(ParseDeriveError { derive })?;
+        let name = path.segments.last().map(|segment| segment.ident.to_string()).unwrap_or_default();
+        model.derives.push((name, path.into_token_stream()));
+    }
+    for attribute in &model_attributes.attributes {
+        let meta = syn::parse_str::<syn::Meta>(attribute).context(ParseAttributeError { attribute })?;
+        model.attributes.push(meta.into_token_stream());
+    }
+    Ok(model)
+}
+
+fn get_type_name_for_schema_ref(cg: &CodeGen, doc_file: &Path, schema: &ReferenceOr<Schema>, as_ref: AsReference) -> Result<TokenStream> {
+    match schema {
+        ReferenceOr::Reference { reference, .. } => {
+            reference.name.as_ref().context(NoNameForRef)?;
+            let ref_key = cg.spec.schema_ref_key(doc_file, reference).context(SpecError)?;
+            let idt = cg.get_type_name(&ref_key)?;
+            match as_ref {
+                AsReference::True => Ok(quote! { &#idt }),
+                AsReference::False => Ok(quote! { #idt }),
+            }
+        }
+        ReferenceOr::Item(schema) => get_type_name_for_schema(cg, doc_file, &schema.common, as_ref),
+    }
+}
+
+fn create_response_type(cg: &CodeGen, doc_file: &Path, rsp: &Response) -> Result<Option<TokenStream>> {
+    if let Some(schema) = &rsp.schema {
+        Ok(Some(get_type_name_for_schema_ref(cg, doc_file, schema, AsReference::False)?))
     } else {
         Ok(None)
     }
@@ -573,22 +2824,48 @@ fn create_function(
     _item: &PathItem,
     operation_verb: &OperationVerb,
     param_re: &Regex,
-    function_name: &str,
+    fname: &TokenStream,
+    deprecated: &TokenStream,
+    resource_id: Option<(&TokenStream, &(TokenStream, ResourceIdPattern))>,
 ) -> Result<TokenStream> {
-    let fname = ident(function_name).context(IdentError {
+    let parameters: Vec<Parameter> = cg
+        .spec
+        .resolve_parameters(doc_file, &operation_verb.operation().parameters)
+        .context(SpecError)?;
+    // constant parameters are not passed in by the caller
+    let (constant_parameters, mut parameters): (Vec<Parameter>, Vec<Parameter>) = parameters.into_iter().partition(is_constant_parameter);
+    // distinct parameters may have the same name, so the ones that follow are given the unique names as their client names
+    let mut unique_names = UniqueIdents::default();
+    unique_names.insert(quote! { operation_config }).context(IdentError {
         file: file!(),
         line: line!(),
     })?;
+    for param in &mut parameters {
+        let name = get_param_name(param)?;
+        let unique_name = unique_names.insert(name.clone()).context(IdentError {
+            file: file!(),
+            line: line!(),
+        })?;
+        if unique_name.to_string() != name.to_string() {
+            param.x_ms_client_name = Some(unique_name.to_string());
+        }
+    }
+    let constants: HashMap<&str, String> = constant_parameters
+        .iter()
+        .map(|p| (p.name.as_str(), get_constant_parameter_value(p)))
+        .collect();
 
     let params = parse_params(param_re, path);
     // println!("path params {:#?}", params);
     let params: Result<Vec<_>> = params
         .iter()
-        .map(|s| {
-            Ok(ident(&s.to_snake_case()).context(IdentError {
+        .map(|s| match (constants.get(s.as_str()), parameters.iter().find(|p| &p.name == s)) {
+            (Some(value), _) => Ok(quote! { #value }),
+            (None, Some(param)) => get_param_name(param),
+            (None, None) => Ok(ident(&s.to_snake_case()).context(IdentError {
                 file: file!(),
                 line: line!(),
-            })?)
+            })?),
         })
         .collect();
     let params = params?;
@@ -596,10 +2873,6 @@ fn create_function(
 
     let fpath = format!("{{}}{}", &format_path(param_re, path));
 
-    let parameters: Vec<Parameter> = cg
-        .spec
-        .resolve_parameters(doc_file, &operation_verb.operation().parameters)
-        .context(SpecError)?;
     let param_names: HashSet<_> = parameters.iter().map(|p| p.name.as_str()).collect();
     let has_param_api_version = param_names.contains("api-version");
     let mut skip = HashSet::new();
@@ -609,52 +2882,85 @@ fn create_function(
     let parameters = parameters.into_iter().filter(|p| !skip.contains(p.name.as_str())).collect();
 
     let fparams = create_function_params(cg, doc_file, &parameters)?;
+    let fdoc = create_function_doc(operation_verb.operation(), &parameters)?;
 
-    // see if there is a body parameter
-    // let fresponse = create_function_return(operation_verb)?;
+    // client-side checks of the parameter constraints
+    let mut ts_validation = TokenStream::new();
+    if cg.config.validation {
+        for param in &parameters {
+            let checks = match (&param.in_, &param.schema) {
+                (ParameterType::Body, Some(schema)) => {
+                    let body = cg.spec.resolve_schema(doc_file, schema).context(SpecError)?;
+                    cg.create_property_checks(doc_file, &param.name, &body)?
+                }
+                // a format type is not a number or a string
+                _ if cg.get_format_type(&param.common).is_some() => TokenStream::new(),
+                _ => create_constraint_checks(&param.name, &param.common),
+            };
+            if checks.is_empty() {
+                continue;
+            }
+            let param_name_var = get_param_name(param)?;
+            let check = quote! {
+                let check = || -> std::result::Result<(), model_validation::ValidationError> {
+                    #checks
+                    Ok(())
+                };
+                check().context(ValidationError)?;
+            };
+            if param.required.unwrap_or(false) || is_array(&param.common) {
+                ts_validation.extend(quote! {
+                    {
+                        let value = &#param_name_var;
+                        #check
+                    }
+                });
+            } else {
+                ts_validation.extend(quote! {
+                    if let Some(value) = &#param_name_var {
+                        #check
+                    }
+                });
+            }
+        }
+    }
 
     let mut ts_request_builder = TokenStream::new();
 
-    let mut is_post = false;
-    let req_verb = match operation_verb {
-        OperationVerb::Get(_) => quote! { req_builder = req_builder.method(http::Method::GET); },
-        OperationVerb::Post(_) => {
-            is_post = true;
-            quote! { req_builder = req_builder.method(http::Method::POST); }
-        }
-        OperationVerb::Put(_) => quote! { req_builder = req_builder.method(http::Method::PUT); },
-        OperationVerb::Patch(_) => quote! { req_builder = req_builder.method(http::Method::PATCH); },
-        OperationVerb::Delete(_) => quote! { req_builder = req_builder.method(http::Method::DELETE); },
-        OperationVerb::Options(_) => quote! { req_builder = req_builder.method(http::Method::OPTIONS); },
-        OperationVerb::Head(_) => quote! { req_builder = req_builder.method(http::Method::HEAD); },
-    };
-    ts_request_builder.extend(req_verb);
-
-    // auth
-    ts_request_builder.extend(quote! {
-        if let Some(token_credential) = operation_config.token_credential() {
-            let token_response = token_credential
-                .get_token(operation_config.token_credential_resource()).await
-                .context(#fname::GetTokenError)?;
-            req_builder = req_builder.header(http::header::AUTHORIZATION, format!("Bearer {}", token_response.token.secret()));
-        }
-    });
-
     // api-version param
     if has_param_api_version {
         if let Some(_api_version) = cg.api_version() {
             ts_request_builder.extend(quote! {
-                url.query_pairs_mut().append_pair("api-version", operation_config.api_version());
+                req.query("api-version", operation_config.api_version());
             });
         }
     }
 
+    // constant params
+    for param in &constant_parameters {
+        let param_name = &param.name;
+        let value = get_constant_parameter_value(param);
+        match param.in_ {
+            ParameterType::Query => ts_request_builder.extend(quote! {
+                req.query(#param_name, #value);
+            }),
+            ParameterType::Header => ts_request_builder.extend(quote! {
+                req.header(#param_name, #value);
+            }),
+            _ => {} // path params are handled above
+        }
+    }
+
     // params
-    let mut has_body_parameter = false;
     for param in &parameters {
         let param_name = &param.name;
         let param_name_var = get_param_name(&param)?;
         let required = param.required.unwrap_or(false);
+        let value = if is_string(&param.common) {
+            quote! { #param_name_var }
+        } else {
+            quote! { &#param_name_var.to_string() }
+        };
         match param.in_ {
             ParameterType::Path => {} // handled above
             ParameterType::Query => {
@@ -666,13 +2972,13 @@ fn create_function(
                             if is_string(&param.common){
                                 quote! {
                                     for value in #param_name_var {
-                                        url.query_pairs_mut().append_pair(#param_name, value);
+                                        req.query(#param_name, value);
                                     }
                                 }
                             } else {
                                 quote! {
                                     for value in #param_name_var {
-                                        url.query_pairs_mut().append_pair(#param_name, value.to_string().as_str());
+                                        req.query(#param_name, &value.to_string());
                                     }
                                 }
                             }
@@ -683,14 +2989,8 @@ fn create_function(
                         CollectionFormat::Pipes => None,
                     }
                 } else {
-                    Some(if is_string(&param.common) {
-                        quote! {
-                            url.query_pairs_mut().append_pair(#param_name, #param_name_var);
-                        }
-                    } else {
-                        quote! {
-                            url.query_pairs_mut().append_pair(#param_name, #param_name_var.to_string().as_str());
-                        }
+                    Some(quote! {
+                        req.query(#param_name, #value);
                     })
                 };
                 if let Some(query_body) = query_body {
@@ -708,42 +3008,38 @@ fn create_function(
             ParameterType::Header => {
                 if required {
                     ts_request_builder.extend(quote! {
-                        req_builder = req_builder.header(#param_name, #param_name_var);
+                        req.header(#param_name, #value);
                     });
                 } else {
                     ts_request_builder.extend(quote! {
                         if let Some(#param_name_var) = #param_name_var {
-                            req_builder = req_builder.header(#param_name, #param_name_var);
+                            req.header(#param_name, #value);
                         }
                     });
                 }
             }
             ParameterType::Body => {
-                has_body_parameter = true;
                 if required {
                     ts_request_builder.extend(quote! {
-                        let req_body = azure_core::to_json(#param_name_var).context(#fname::SerializeError)?;
+                        req.json(#param_name_var).context(RuntimeError)?;
                     });
                 } else {
                     ts_request_builder.extend(quote! {
-                        let req_body =
-                            if let Some(#param_name_var) = #param_name_var {
-                                azure_core::to_json(#param_name_var).context(#fname::SerializeError)?
-                            } else {
-                                bytes::Bytes::from_static(azure_core::EMPTY_BODY)
-                            };
+                        if let Some(#param_name_var) = #param_name_var {
+                            req.json(#param_name_var).context(RuntimeError)?;
+                        }
                     });
                 }
             }
             ParameterType::Form => {
                 if required {
                     ts_request_builder.extend(quote! {
-                        req_builder = req_builder.form(#param_name_var);
+                        req.form(#param_name, #value);
                     });
                 } else {
                     ts_request_builder.extend(quote! {
                         if let Some(#param_name_var) = #param_name_var {
-                            req_builder = req_builder.form(#param_name_var);
+                            req.form(#param_name, #value);
                         }
                     });
                 }
@@ -751,18 +3047,15 @@ fn create_function(
         }
     }
 
-    if !has_body_parameter {
-        ts_request_builder.extend(quote! {
-            let req_body = bytes::Bytes::from_static(azure_core::EMPTY_BODY);
-        });
-    }
-
-    // if it is a post and there is no body, set the Content-Length to 0
-    if is_post && !has_body_parameter {
-        ts_request_builder.extend(quote! {
-            req_builder = req_builder.header(http::header::CONTENT_LENGTH, 0);
-        });
-    }
+    let req_method = match operation_verb {
+        OperationVerb::Get(_) => quote! { http::Method::GET },
+        OperationVerb::Post(_) => quote! { http::Method::POST },
+        OperationVerb::Put(_) => quote! { http::Method::PUT },
+        OperationVerb::Patch(_) => quote! { http::Method::PATCH },
+        OperationVerb::Delete(_) => quote! { http::Method::DELETE },
+        OperationVerb::Options(_) => quote! { http::Method::OPTIONS },
+        OperationVerb::Head(_) => quote! { http::Method::HEAD },
+    };
 
     let responses = &operation_verb.operation().responses;
     let success_responses = get_success_responses(responses);
@@ -770,8 +3063,15 @@ fn create_function(
     let is_single_response = success_responses.len() == 1;
     let has_default_response = has_default_response(responses);
 
+    // the type of a successful response, in the operation module, where the models may be shadowed
+    let rsp_type = if is_single_response {
+        let tp = create_response_type(cg, doc_file, &success_responses[0])?.unwrap_or(quote! { () });
+        qualify_shadowed_models(tp)
+    } else {
+        quote! { self::Response }
+    };
     let fresponse = if is_single_response {
-        let tp = create_response_type(&success_responses[0])?.unwrap_or(quote! { () });
+        let tp = create_response_type(cg, doc_file, &success_responses[0])?.unwrap_or(quote! { () });
         quote! { std::result::Result<#tp, #fname::Error> }
     } else {
         quote! { std::result::Result<#fname::Response, #fname::Error> }
@@ -781,16 +3081,23 @@ fn create_function(
     if !is_single_response {
         let mut success_responses_ts = TokenStream::new();
         for (status_code, rsp) in &success_responses {
-            let tp = create_response_type(rsp)?;
+            let tp = create_response_type(cg, doc_file, rsp)?;
             let tp = match tp {
-                Some(tp) => quote! { (#tp) },
+                Some(tp) => {
+                    let tp = qualify_shadowed_models(tp);
+                    quote! { (#tp) }
+                }
                 None => quote! {},
             };
             let enum_type_name = ident(&get_response_type_name(status_code)).context(IdentError {
                 file: file!(),
                 line: line!(),
             })?;
-            success_responses_ts.extend(quote! { #enum_type_name#tp, })
+            let doc = create_doc(&rsp.description);
+            success_responses_ts.extend(quote! {
+                #doc
+                #enum_type_name#tp,
+            })
         }
         response_enum.extend(quote! {
             #[derive(Debug)]
@@ -802,9 +3109,9 @@ fn create_function(
 
     let mut error_responses_ts = TokenStream::new();
     for (status_code, rsp) in &error_responses {
-        let tp = create_response_type(rsp)?;
+        let tp = create_response_type(cg, doc_file, rsp)?.map(qualify_shadowed_models);
         let tp = match tp {
-            Some(tp) => quote! { value: models::#tp, },
+            Some(tp) => quote! { value: #tp, },
             None => quote! {},
         };
         let response_type = &get_response_type_name(status_code);
@@ -821,12 +3128,15 @@ fn create_function(
     if !has_default_response {
         error_responses_ts.extend(quote! { UnexpectedResponse { status_code: http::StatusCode, body: bytes::Bytes }, });
     }
+    if cg.config.validation {
+        error_responses_ts.extend(quote! { ValidationError { source: models::model_validation::ValidationError }, });
+    }
 
     let mut match_status = TokenStream::new();
     for (status_code, rsp) in &success_responses {
         match status_code {
             autorust_openapi::StatusCode::Code(_) => {
-                let tp = create_response_type(rsp)?;
+                let tp = create_response_type(cg, doc_file, rsp)?.map(qualify_shadowed_models);
                 let status_code_name = ident(&get_status_code_name(status_code)).context(IdentError {
                     file: file!(),
                     line: line!(),
@@ -840,8 +3150,7 @@ fn create_function(
                         Some(tp) => {
                             match_status.extend(quote! {
                                 http::StatusCode::#status_code_name => {
-                                    let rsp_body = rsp.body();
-                                    let rsp_value: #tp = serde_json::from_slice(rsp_body).context(#fname::DeserializeError { body: rsp_body.clone() })?;
+                                    let rsp_value: #tp = autorust_runtime::from_json(&rsp).context(RuntimeError)?;
                                     Ok(rsp_value)
                                 }
                             });
@@ -859,16 +3168,15 @@ fn create_function(
                         Some(tp) => {
                             match_status.extend(quote! {
                                 http::StatusCode::#status_code_name => {
-                                    let rsp_body = rsp.body();
-                                    let rsp_value: #tp = serde_json::from_slice(rsp_body).context(#fname::DeserializeError { body: rsp_body.clone() })?;
-                                    Ok(#fname::Response::#response_type_name(rsp_value))
+                                    let rsp_value: #tp = autorust_runtime::from_json(&rsp).context(RuntimeError)?;
+                                    Ok(self::Response::#response_type_name(rsp_value))
                                 }
                             });
                         }
                         None => {
                             match_status.extend(quote! {
                                 http::StatusCode::#status_code_name => {
-                                    Ok(#fname::Response::#response_type_name)
+                                    Ok(self::Response::#response_type_name)
                                 }
                             });
                         }
@@ -881,7 +3189,7 @@ fn create_function(
     for (status_code, rsp) in &error_responses {
         match status_code {
             autorust_openapi::StatusCode::Code(_) => {
-                let tp = create_response_type(rsp)?;
+                let tp = create_response_type(cg, doc_file, rsp)?.map(qualify_shadowed_models);
                 let status_code_name = ident(&get_status_code_name(status_code)).context(IdentError {
                     file: file!(),
                     line: line!(),
@@ -894,16 +3202,15 @@ fn create_function(
                     Some(tp) => {
                         match_status.extend(quote! {
                             http::StatusCode::#status_code_name => {
-                                let rsp_body = rsp.body();
-                                let rsp_value: #tp = serde_json::from_slice(rsp_body).context(#fname::DeserializeError { body: rsp_body.clone() })?;
-                                #fname::#response_type_name{value: rsp_value}.fail()
+                                let rsp_value: #tp = autorust_runtime::from_json(&rsp).context(RuntimeError)?;
+                                #response_type_name{value: rsp_value}.fail()
                             }
                         });
                     }
                     None => {
                         match_status.extend(quote! {
                             http::StatusCode::#status_code_name => {
-                                #fname::#response_type_name{}.fail()
+                                #response_type_name{}.fail()
                             }
                         });
                     }
@@ -918,21 +3225,20 @@ fn create_function(
             match status_code {
                 autorust_openapi::StatusCode::Code(_) => {}
                 autorust_openapi::StatusCode::Default => {
-                    let tp = create_response_type(rsp)?;
+                    let tp = create_response_type(cg, doc_file, rsp)?.map(qualify_shadowed_models);
                     match tp {
                         Some(tp) => {
                             match_status.extend(quote! {
                                 status_code => {
-                                    let rsp_body = rsp.body();
-                                    let rsp_value: #tp = serde_json::from_slice(rsp_body).context(#fname::DeserializeError { body: rsp_body.clone() })?;
-                                    #fname::DefaultResponse{status_code, value: rsp_value}.fail()
+                                    let rsp_value: #tp = autorust_runtime::from_json(&rsp).context(RuntimeError)?;
+                                    DefaultResponse{status_code, value: rsp_value}.fail()
                                 }
                             });
                         }
                         None => {
                             match_status.extend(quote! {
                                 status_code => {
-                                    #fname::DefaultResponse{status_code}.fail()
+                                    DefaultResponse{status_code}.fail()
                                 }
                             });
                         }
@@ -943,29 +3249,153 @@ fn create_function(
     } else {
         match_status.extend(quote! {
             status_code => {
-                let rsp_body = rsp.body();
-                #fname::UnexpectedResponse{status_code, body: rsp_body.clone()}.fail()
+                UnexpectedResponse{status_code, body: rsp.body().clone()}.fail()
             }
         });
     }
 
+    let mut args = vec![quote! { operation_config }];
+    for param in &parameters {
+        args.push(get_param_name(param)?);
+    }
+    let mod_fparams = qualify_shadowed_models(fparams.clone());
+    let fname_str = fname.to_string();
+    let fname_str = fname_str.trim_start_matches("r#");
+
+    // the spans of a traced operation are identified by its operation ID, method and path template, without the parameter values
+    let mut operation = TokenStream::new();
+    if cg.config.tracing {
+        let operation_id = operation_verb.operation().operation_id.as_deref().unwrap_or(fname_str);
+        let method = operation_verb.verb_name().to_uppercase();
+        let path = path.split('?').next().unwrap_or_default();
+        operation.extend(quote! {
+            pub(crate) const OPERATION: autorust_runtime::trace::Operation = autorust_runtime::trace::Operation {
+                id: #operation_id,
+                method: #method,
+                path: #path,
+            };
+        });
+    }
+    let traced = |operation_path: TokenStream, body: TokenStream| {
+        if cg.config.tracing {
+            quote! { autorust_runtime::trace::instrument(&#operation_path, async move { #body }).await }
+        } else {
+            body
+        }
+    };
+
+    // the pages of a pageable operation link to the next ones
+    let mut paging = TokenStream::new();
+    if let (Some(pageable), true) = (&operation_verb.operation().x_ms_pageable, is_single_response) {
+        if let Some(next_link) = get_next_link(cg, doc_file, &success_responses[0], pageable.next_link_name.as_deref())? {
+            let doc = format!(" Gets the pages of `{}`, following the links to the next pages.", fname_str);
+            // the pages borrow the configuration, but not the parameters
+            let mut page_params = vec![quote! { operation_config: &'a crate::OperationConfig }];
+            for param in &parameters {
+                let name = get_param_name(param)?;
+                let tp = qualify_shadowed_models(get_param_type(cg, doc_file, param)?);
+                page_params.push(quote! { #name: #tp });
+            }
+            let with_operation = if cg.config.tracing {
+                quote! { .with_operation(&OPERATION) }
+            } else {
+                TokenStream::new()
+            };
+            paging.extend(quote! {
+                #[doc = #doc]
+                pub fn pages<'a>(#(#page_params),*) -> autorust_runtime::Pager<'a, #rsp_type, Error> {
+                    autorust_runtime::Pager::new(
+                        operation_config,
+                        build_request(#(#args),*),
+                        handle_response,
+                        |page| #next_link,
+                        |source| Error::RuntimeError { source },
+                    )
+                    #with_operation
+                }
+            });
+        }
+    }
+
+    // a long-running operation is polled until it is done
+    let mut long_running = TokenStream::new();
+    if operation_verb.operation().x_ms_long_running_operation == Some(true) {
+        let doc = format!(" Calls `{}` and waits until the long-running operation is done.", fname_str);
+        let body = traced(
+            quote! { OPERATION },
+            quote! {
+                let req = build_request(#(#args),*)?;
+                let method = req.method().clone();
+                let url = req.url().clone();
+                let rsp = operation_config.send(req).await.context(RuntimeError)?;
+                let rsp = autorust_runtime::lro::wait_for_completion(operation_config, &method, &url, rsp)
+                    .await
+                    .context(RuntimeError)?;
+                handle_response(rsp)
+            },
+        );
+        long_running.extend(quote! {
+            #[doc = #doc]
+            pub async fn wait_for_completion(#mod_fparams) -> std::result::Result<#rsp_type, Error> {
+                #body
+            }
+        });
+    }
+
+    // the blocking variants send the same request and handle the same response, without an async runtime
+    let mut blocking = TokenStream::new();
+    if cg.config.blocking {
+        let doc = format!(" Calls `{}` and blocks until it is done.", fname_str);
+        let mut body = quote! {
+            let req = build_request(#(#args),*)?;
+            let rsp = operation_config.send_blocking(req).context(RuntimeError)?;
+            handle_response(rsp)
+        };
+        if cg.config.tracing {
+            body = quote! { autorust_runtime::trace::instrument_blocking(&OPERATION, move || { #body }) };
+        }
+        blocking.extend(quote! {
+            #[doc = #doc]
+            pub fn blocking(#mod_fparams) -> std::result::Result<#rsp_type, Error> {
+                #body
+            }
+        });
+        if !long_running.is_empty() {
+            let doc = format!(" Calls `{}` and blocks until the long-running operation is done.", fname_str);
+            blocking.extend(quote! {
+                #[doc = #doc]
+                pub fn wait_for_completion_blocking(#mod_fparams) -> std::result::Result<#rsp_type, Error> {
+                    autorust_runtime::block_on(wait_for_completion(#(#args),*))
+                }
+            });
+        }
+    }
+
+    let id_func = match resource_id {
+        Some((id_fname, (id_type, pattern))) => {
+            create_resource_id_function(cg, doc_file, &parameters, fname, &fresponse, deprecated, id_fname, id_type, pattern)?
+        }
+        None => TokenStream::new(),
+    };
+
+    let body = traced(
+        quote! { #fname::OPERATION },
+        quote! {
+            let req = #fname::build_request(#(#args),*)?;
+            let rsp = operation_config.send(req).await.context(#fname::RuntimeError)?;
+            #fname::handle_response(rsp)
+        },
+    );
     let func = quote! {
+        #fdoc
+        #deprecated
         pub async fn #fname(#fparams) -> #fresponse {
-            let http_client = operation_config.http_client();
-            let url_str = &format!(#fpath, operation_config.base_path(), #url_str_args);
-            let mut url = url::Url::parse(url_str).context(#fname::ParseUrlError)?;
-            let mut req_builder = http::request::Builder::new();
-            #ts_request_builder
-            req_builder = req_builder.uri(url.as_str());
-            let req = req_builder.body(req_body).context(#fname::BuildRequestError)?;
-            let rsp = http_client.execute_request(req).await.context(#fname::ExecuteRequestError)?;
-            match rsp.status() {
-                #match_status
-            }
+            #body
         }
+        #deprecated
         pub mod #fname {
             use crate::{models, models::*};
-            use snafu::Snafu;
+            use snafu::{ResultExt, Snafu};
 
             #response_enum
 
@@ -973,22 +3403,952 @@ fn create_function(
             #[snafu(visibility(pub(crate)))]
             pub enum Error {
                 #error_responses_ts
-                ParseUrlError { source: url::ParseError },
-                BuildRequestError { source: http::Error },
-                ExecuteRequestError { source: Box<dyn std::error::Error + Sync + Send> },
-                SerializeError { source: Box<dyn std::error::Error + Sync + Send> },
-                DeserializeError { source: serde_json::Error, body: bytes::Bytes },
-                GetTokenError { source: azure_core::errors::AzureError },
+                RuntimeError { source: autorust_runtime::Error },
+            }
+
+            #operation
+
+            pub(crate) fn build_request(#mod_fparams) -> std::result::Result<autorust_runtime::Request, Error> {
+                #ts_validation
+                let url_str = &format!(#fpath, operation_config.base_path(), #url_str_args);
+                let mut req = autorust_runtime::Request::new(#req_method, url_str).context(RuntimeError)?;
+                #ts_request_builder
+                Ok(req)
+            }
+
+            pub(crate) fn handle_response(rsp: autorust_runtime::Response) -> std::result::Result<#rsp_type, Error> {
+                match rsp.status() {
+                    #match_status
+                }
             }
+
+            #paging
+            #long_running
+            #blocking
         }
+        #id_func
     };
     Ok(TokenStream::from(func))
 }
 
-pub fn create_mod(api_version: &str) -> TokenStream {
+/// Gets the link to the next page from a page of a pageable operation, when the page has the property with the link.
+fn get_next_link(cg: &CodeGen, doc_file: &Path, rsp: &Response, next_link_name: Option<&str>) -> Result<Option<TokenStream>> {
+    let (schema, next_link_name) = match (&rsp.schema, next_link_name) {
+        (Some(schema), Some(next_link_name)) => (schema, next_link_name),
+        _ => return Ok(None),
+    };
+    let schema = cg.spec.resolve_schema(doc_file, schema).context(SpecError)?;
+    if matches!(&schema.ref_key, Some(ref_key) if cg.is_mapped(ref_key)) {
+        return Ok(None);
+    }
+    let property = match schema.schema.properties.get(next_link_name) {
+        Some(ReferenceOr::Item(property)) if is_string(&property.common) => property,
+        _ => return Ok(None),
+    };
+    let field_name = ident(&get_client_name(next_link_name, &property.common).to_snake_case()).context(IdentError {
+        file: file!(),
+        line: line!(),
+    })?;
+    if schema.schema.required.iter().any(|required| required == next_link_name) {
+        Ok(Some(quote! { Some(page.#field_name.clone()) }))
+    } else {
+        Ok(Some(quote! { page.#field_name.clone() }))
+    }
+}
+
+/// Qualifies the models named `Error` or `Response` in a type, as those are shadowed in the module of an operation.
+fn qualify_shadowed_models(tokens: TokenStream) -> TokenStream {
+    let mut qualified = TokenStream::new();
+    let mut is_path = false;
+    for token in tokens {
+        match &token {
+            TokenTree::Ident(name) if !is_path && (name == "Error" || name == "Response") => qualified.extend(quote! { models::#name }),
+            TokenTree::Group(group) => {
+                let mut qualified_group = Group::new(group.delimiter(), qualify_shadowed_models(group.stream()));
+                qualified_group.set_span(group.span());
+                qualified.extend(Some(TokenTree::Group(qualified_group)));
+            }
+            _ => qualified.extend(Some(token.clone())),
+        }
+        is_path = matches!(&token, TokenTree::Punct(punct) if punct.as_char() == ':');
+    }
+    qualified
+}
+
+/// Creates a function that takes the path parameters of an operation from a typed resource ID, and calls the operation.
+/// There is none when a path parameter from the resource ID is not a string.
+fn create_resource_id_function(
+    cg: &CodeGen,
+    doc_file: &Path,
+    parameters: &[Parameter],
+    fname: &TokenStream,
+    fresponse: &TokenStream,
+    deprecated: &TokenStream,
+    id_fname: &TokenStream,
+    id_type: &TokenStream,
+    pattern: &ResourceIdPattern,
+) -> Result<TokenStream> {
+    let id_parameters = pattern.parameters();
+    let id_fields = resource_id::get_field_names(pattern).context(ResourceIdError)?;
+    // the resource ID is passed in place of its path parameters, so its name must differ from the other parameters
+    let mut names = UniqueIdents::default();
+    names.insert(quote! { operation_config }).context(IdentError {
+        file: file!(),
+        line: line!(),
+    })?;
+    let mut id_params = Vec::new();
+    for param in parameters {
+        let id_field = match param.in_ {
+            ParameterType::Path => id_parameters.iter().position(|id_param| id_param == &param.name),
+            _ => None,
+        };
+        match id_field {
+            Some(_) if !is_string(&param.common) => return Ok(TokenStream::new()),
+            Some(i) => id_params.push((param, Some(&id_fields[i]))),
+            None => {
+                names.insert(get_param_name(param)?).context(IdentError {
+                    file: file!(),
+                    line: line!(),
+                })?;
+                id_params.push((param, None));
+            }
+        }
+    }
+    let id = names.insert(quote! { id }).context(IdentError {
+        file: file!(),
+        line: line!(),
+    })?;
+    let mut params = vec![
+        quote! { operation_config: &crate::OperationConfig },
+        quote! { #id: &resource_ids::#id_type },
+    ];
+    let mut args = vec![quote! { operation_config }];
+    for (param, id_field) in id_params {
+        match id_field {
+            Some(id_field) => args.push(quote! { &#id.#id_field }),
+            None => {
+                let name = get_param_name(param)?;
+                let tp = get_param_type(cg, doc_file, param)?;
+                params.push(quote! { #name: #tp });
+                args.push(quote! { #name });
+            }
+        }
+    }
+    let doc = format!(
+        " Calls `{}` with the path parameters from a resource ID.",
+        fname.to_string().trim_start_matches("r#")
+    );
+    Ok(quote! {
+        #[doc = #doc]
+        #deprecated
+        pub async fn #id_fname(#(#params),*) -> #fresponse {
+            #fname(#(#args),*).await
+        }
+    })
+}
+
+/// Creates the documentation for an operation from its summary and description, listing the parameters and the responses.
+fn create_function_doc(operation: &Operation, parameters: &[Parameter]) -> Result<TokenStream> {
+    let mut sections = Vec::new();
+    if let Some(summary) = &operation.summary {
+        sections.push(summary.clone());
+    }
+    if let Some(description) = &operation.description {
+        if operation.summary.as_ref() != Some(description) {
+            sections.push(description.clone());
+        }
+    }
+    let mut arguments = Vec::new();
+    for param in parameters {
+        let name = get_param_name(param)?.to_string();
+        let name = name.trim_start_matches("r#");
+        match &param.description {
+            Some(description) => arguments.push(format!("* `{}`: {}", name, to_single_line(description))),
+            None => arguments.push(format!("* `{}`", name)),
+        }
+    }
+    if arguments.len() > 0 {
+        sections.push(format!("# Arguments\n\n{}", arguments.join("\n")));
+    }
+    let responses: Vec<_> = operation
+        .responses
+        .iter()
+        .map(|(status_code, rsp)| {
+            let status_code = match status_code {
+                autorust_openapi::StatusCode::Code(code) => code.to_string(),
+                autorust_openapi::StatusCode::Default => "default".to_owned(),
+            };
+            if rsp.description.trim().is_empty() {
+                format!("* {}", status_code)
+            } else {
+                format!("* {}: {}", status_code, to_single_line(&rsp.description))
+            }
+        })
+        .collect();
+    if responses.len() > 0 {
+        sections.push(format!("# Responses\n\n{}", responses.join("\n")));
+    }
+    Ok(create_doc(&sections.join("\n\n")))
+}
+
+lazy_static::lazy_static! {
+    static ref DEPRECATED_RE: Regex =
+        Regex::new(r"(?i)^\W*(deprecated\b|this (property|field|parameter|model|type|operation|api|value) (is|has been) deprecated)").unwrap();
+    static ref REPLACEMENT_RE: Regex =
+        Regex::new(r"(?i)\b(use|replaced by|superseded by|in favou?r of)\s+(the\s+)?`?([a-z][a-z0-9]*_[a-z0-9_]+)`?").unwrap();
+}
+
+/// Whether a description marks what it describes as deprecated, such as `Deprecated. Use ... instead.`
+/// This is only a fallback for what is not marked with `x-ms-deprecated` or `deprecated: true`.
+fn is_deprecated_description(description: Option<&str>) -> bool {
+    description.map_or(false, |description| DEPRECATED_RE.is_match(description))
+}
+
+fn is_deprecated_operation(operation: &Operation) -> bool {
+    operation.deprecated == Some(true)
+        || is_deprecated_description(operation.summary.as_deref())
+        || is_deprecated_description(operation.description.as_deref())
+}
+
+/// Gets the note for a deprecated operation, naming the function of the replacement operation
+/// when its `x-ms-deprecated` or else its description mentions its operationId.
+fn get_operation_deprecation_note(
+    operation: &Operation,
+    deprecation: Option<&Deprecation>,
+    operation_paths: &HashMap<String, String>,
+) -> Option<String> {
+    if let Some(deprecation) = deprecation {
+        let replacement = deprecation
+            .replaced_by
+            .as_ref()
+            .and_then(|replaced_by| operation_paths.get(&replaced_by.to_lowercase()));
+        match (replacement, &deprecation.description) {
+            (Some(operation_path), _) => return Some(format!("use {} instead", operation_path)),
+            (None, Some(description)) => return Some(to_single_line(description)),
+            (None, None) => {}
+        }
+    }
+    let descriptions = [operation.description.as_deref(), operation.summary.as_deref()];
+    let descriptions = descriptions.iter().filter_map(|description| *description);
+    for description in descriptions.clone() {
+        for captures in REPLACEMENT_RE.captures_iter(description) {
+            if let Some(operation_path) = operation_paths.get(&captures[3].to_lowercase()) {
+                return Some(format!("use {} instead", operation_path));
+            }
+        }
+    }
+    descriptions
+        .filter(|description| is_deprecated_description(Some(description)))
+        .map(to_single_line)
+        .next()
+}
+
+/// Creates the `#[deprecated]` attribute for a schema, property or enum value that is marked as deprecated,
+/// or else with a description that marks it as deprecated.
+fn create_schema_deprecated(deprecation: Option<&Deprecation>, description: Option<&str>) -> TokenStream {
+    match deprecation {
+        Some(deprecation) => {
+            let note = match (&deprecation.description, &deprecation.replaced_by) {
+                (Some(description), _) => Some(to_single_line(description)),
+                (None, Some(replaced_by)) => Some(format!("replaced by {}", replaced_by)),
+                (None, None) => description
+                    .filter(|description| is_deprecated_description(Some(description)))
+                    .map(to_single_line),
+            };
+            create_deprecated(note.as_deref())
+        }
+        None if is_deprecated_description(description) => create_deprecated(description.map(to_single_line).as_deref()),
+        None => TokenStream::new(),
+    }
+}
+
+fn create_deprecated(note: Option<&str>) -> TokenStream {
+    match note {
+        Some(note) => quote! { #[deprecated(note = #note)] },
+        None => quote! { #[deprecated] },
+    }
+}
+
+/// Joins the lines of a description, so that it fits in a list item.
+fn to_single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn create_mod(api_version: &str, target: Target, base_path: Option<&str>, blocking: bool) -> TokenStream {
+    let config = target.create_config_function(base_path, blocking);
     quote! {
         pub mod models;
         pub mod operations;
         pub const API_VERSION: &str = #api_version;
+        #config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GROUPS_SPEC: &str = "tests/specs/groups.json";
+    const PRUNE_SPEC: &str = "tests/specs/prune.json";
+    const RESOURCE_IDS_SPEC: &str = "tests/specs/resource_ids.json";
+    const INPUT_MODELS_SPEC: &str = "tests/specs/input_models.json";
+    const DEFAULTS_SPEC: &str = "tests/specs/defaults.json";
+    const VALIDATION_SPEC: &str = "tests/specs/validation.json";
+    const CONSTANTS_SPEC: &str = "tests/specs/constants.json";
+    const SECRETS_SPEC: &str = "tests/specs/secrets.json";
+    const MODEL_ATTRIBUTES_SPEC: &str = "tests/specs/model_attributes.json";
+    const FLATTEN_SPEC: &str = "tests/specs/flatten.json";
+    const DUPLICATE_NAMES_SPEC: &str = "tests/specs/duplicate_names.json";
+    const BLOCKING_SPEC: &str = "tests/specs/blocking.json";
+    const SAME_NAMES_SPECS: &[&str] = &["tests/specs/same_names/a.json", "tests/specs/same_names/b.json"];
+
+    fn create_groups_codegen() -> CodeGen {
+        CodeGen::new(Config {
+            input_files: vec![PathBuf::from(GROUPS_SPEC)],
+            feature_name: Some("package-2021".to_owned()),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_group_features() {
+        assert_eq!(
+            create_groups_codegen().group_features(),
+            vec!["package-2021-gadgets", "package-2021-widgets"]
+        );
+    }
+
+    #[test]
+    fn test_type_cfgs() {
+        let cg = create_groups_codegen();
+        let type_cfgs = cg.create_type_cfgs(&cg.all_schemas().unwrap());
+        let cfg = |type_name: &str| type_cfgs[type_name].to_string();
+        let features = |features: &[&str]| quote! { #[cfg(any(#(feature = #features),*))] }.to_string();
+        assert_eq!(cfg("Widget"), features(&["package-2021", "package-2021-widgets"]));
+        assert_eq!(cfg("Gadget"), features(&["package-2021", "package-2021-gadgets"]));
+        assert_eq!(
+            cfg("Tag"),
+            features(&["package-2021", "package-2021-gadgets", "package-2021-widgets"])
+        );
+        // the operations without a group and the unused models are only enabled by the feature of the module
+        assert_eq!(cfg("Status"), features(&["package-2021"]));
+        assert_eq!(cfg("Unused"), features(&["package-2021"]));
+    }
+
+    #[test]
+    fn test_no_type_cfgs_without_feature() {
+        let cg = CodeGen::new(Config {
+            input_files: vec![PathBuf::from(GROUPS_SPEC)],
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(cg.group_features().is_empty());
+        assert!(cg.create_type_cfgs(&cg.all_schemas().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_has_integer_enums() {
+        let cg = create_groups_codegen();
+        assert!(!cg.has_integer_enums());
+        cg.create_models().unwrap();
+        assert!(cg.has_integer_enums());
+        let cg = create_prune_codegen();
+        cg.create_models().unwrap();
+        assert!(!cg.has_integer_enums());
+    }
+
+    fn create_prune_codegen() -> CodeGen {
+        let mut schema_types = HashMap::new();
+        schema_types.insert(
+            RefKey {
+                file_path: PathBuf::from(PRUNE_SPEC),
+                name: "Legacy".to_owned(),
+            },
+            "crate::Legacy".to_owned(),
+        );
+        CodeGen::new(Config {
+            input_files: vec![PathBuf::from(PRUNE_SPEC)],
+            prune_models: true,
+            schema_types,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn get_names(ref_keys: impl IntoIterator<Item = RefKey>) -> Vec<String> {
+        let mut names: Vec<_> = ref_keys.into_iter().map(|ref_key| ref_key.name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_reachable_schemas() {
+        let cg = create_prune_codegen();
+        // the schemas that derive from a reachable schema with a discriminator are reachable, along with what they reference
+        assert_eq!(
+            get_names(cg.all_schemas().unwrap().into_iter().map(|(ref_key, _)| ref_key)),
+            vec!["Cat", "Kitten", "Owner", "Pet", "Toy"]
+        );
+    }
+
+    #[test]
+    fn test_pruned_schemas() {
+        // the schemas that are mapped to a configured type are not pruned, as no models are created for them anyway
+        assert_eq!(get_names(create_prune_codegen().pruned_schemas().unwrap()), vec!["Unused"]);
+    }
+
+    #[test]
+    fn test_resource_id_function_name_differs_from_parameters() {
+        let cg = CodeGen::new(Config {
+            input_files: vec![PathBuf::from(RESOURCE_IDS_SPEC)],
+            resource_ids: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let operations = cg.create_operations().unwrap();
+        let code = operations.parts.iter().map(|part| part.content.to_string()).collect::<String>();
+        assert!(code.contains(&quote! { id_2: &resource_ids::WidgetId, id: &str }.to_string()));
+        assert!(code.contains(&quote! { get(operation_config, &id_2.subscription_id, &id_2.widget_name, id) }.to_string()));
+    }
+
+    #[test]
+    fn test_input_models() {
+        let cg = CodeGen::new(Config {
+            input_files: vec![PathBuf::from(INPUT_MODELS_SPEC)],
+            input_models: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let mut input_type_names: Vec<_> = cg
+            .input_type_names
+            .iter()
+            .map(|(ref_key, input_type_name)| (ref_key.name.as_str(), input_type_name.as_str()))
+            .collect();
+        input_type_names.sort();
+        // the definition named `WidgetInput` keeps its name
+        assert_eq!(
+            input_type_names,
+            [
+                ("Part", "PartInput"),
+                ("Parts", "PartsInput"),
+                ("Resource", "ResourceInput"),
+                ("Widget", "WidgetInput2"),
+                ("WidgetProperties", "WidgetPropertiesInput"),
+            ]
+        );
+
+        let models = cg.create_models().unwrap().to_file().to_string();
+        assert!(models.contains("pub struct WidgetInput {"));
+        assert!(models.contains(&quote! { pub resource: ResourceInput, }.to_string()));
+        assert!(models.contains(&quote! { pub properties: Option<WidgetPropertiesInput>, }.to_string()));
+        assert!(models.contains(&quote! { pub tags: Vec<Tag>, }.to_string()));
+        assert!(models.contains(&quote! { pub parts: Option<PartsInput>, }.to_string()));
+        assert!(models.contains(&quote! { pub type PartsInput = Vec<PartInput>; }.to_string()));
+        assert!(!models.contains("TagInput"));
+
+        let operations = cg.create_operations().unwrap().to_file().to_string();
+        assert!(operations.contains(&quote! { widget: &WidgetInput2 }.to_string()));
+        assert!(operations.contains(&quote! { Result<Widget, create_or_update::Error> }.to_string()));
+    }
+
+    #[test]
+    fn test_constructors() {
+        let cg = CodeGen::new(Config {
+            input_files: vec![PathBuf::from(DEFAULTS_SPEC)],
+            ..Default::default()
+        })
+        .unwrap();
+        let models = cg.create_models().unwrap().to_file().to_string();
+        // the required fields without a default are the parameters, a default is set as a literal of the type of the field,
+        // and a default that does not fit in the type is left out
+        let widget_new = quote! {
+            impl Widget {
+                pub fn new(name: String) -> Self {
+                    Self {
+                        count: 5,
+                        enabled: Some(true),
+                        kind: widget::Kind::Widget,
+                        label: Some("none".to_owned()),
+                        name,
+                        ratio: Some(0.5),
+                        size: None,
+                        tags: Vec::new(),
+                        weight: Some(3000000000)
+                    }
+                }
+            }
+        };
+        assert!(models.contains(&widget_new.to_string()));
+        assert!(!models.contains("impl Default for Widget"));
+        // a model without parameters implements `Default` with its constructor
+        let settings_new = quote! {
+            impl Settings {
+                pub fn new() -> Self {
+                    Self {
+                        note: None,
+                        retries: Some(3)
+                    }
+                }
+            }
+            impl Default for Settings {
+                fn default() -> Self {
+                    Self::new()
+                }
+            }
+        };
+        assert!(models.contains(&settings_new.to_string()));
+    }
+
+    #[test]
+    fn test_validation() {
+        let mut format_types = HashMap::new();
+        format_types.insert(
+            TypeFormat {
+                type_: "string".to_owned(),
+                format: "uuid".to_owned(),
+            },
+            "uuid::Uuid".to_owned(),
+        );
+        let cg = CodeGen::new(Config {
+            input_files: vec![PathBuf::from(VALIDATION_SPEC)],
+            validation: true,
+            format_types,
+            ..Default::default()
+        })
+        .unwrap();
+        let models = cg.create_models().unwrap().to_file().to_string();
+        let part_validate = quote! {
+            impl Part {
+                pub fn validate(&self) -> std::result::Result<(), model_validation::ValidationError> {
+                    if let Some(value) = &self.serial {
+                        model_validation::length("serial", value, Some(1u64), Some(8u64))?;
+                    }
+                    Ok(())
+                }
+            }
+        };
+        assert!(models.contains(&part_validate.to_string()));
+        // the nested models are validated by their own `validate`, and a format type is not checked
+        let widget_validate = quote! {
+            impl Widget {
+                pub fn validate(&self) -> std::result::Result<(), model_validation::ValidationError> {
+                    if let Some(value) = &self.name {
+                        {
+                            lazy_static::lazy_static! {
+                                static ref PATTERN: Option<regex::Regex> = regex::Regex::new("^[a-z]+$").ok();
+                            }
+                            model_validation::pattern("name", value, "^[a-z]+$", PATTERN.as_ref())?;
+                        }
+                    }
+                    let value = &self.parts;
+                    model_validation::items("parts", value, None, Some(4u64), false)?;
+                    for item in value.iter() {
+                        item.validate().map_err(|err| err.within("parts"))?;
+                    }
+                    if let Some(value) = &self.size {
+                        model_validation::range("size", *value as f64, Some(1f64), false, Some(100f64), true)?;
+                    }
+                    if let Some(value) = &self.spare {
+                        value.validate().map_err(|err| err.within("spare"))?;
+                    }
+                    Ok(())
+                }
+            }
+        };
+        assert!(models.contains(&widget_validate.to_string()));
+
+        let operations = cg.create_operations().unwrap();
+        let code = operations.parts.iter().map(|part| part.content.to_string()).collect::<String>();
+        assert!(code.contains(&quote! { model_validation::pattern("widgetName", value, "^[a-z]+$", PATTERN.as_ref())?; }.to_string()));
+        assert!(code.contains(
+            &quote! {
+                if let Some(value) = &count {
+                    let check = || -> std::result::Result<(), model_validation::ValidationError> {
+                        model_validation::range("count", *value as f64, None, false, Some(10f64), false)?;
+                        Ok(())
+                    };
+                    check().context(ValidationError)?;
+                }
+            }
+            .to_string()
+        ));
+        // the parameter with a format type is not checked
+        assert!(!code.contains(&quote! { let value = &version; }.to_string()));
+        assert!(!code.contains(&quote! { if let Some(value) = &version }.to_string()));
+        // the items of an array body are validated
+        assert!(code.contains(
+            &quote! {
+                {
+                    let value = &parts;
+                    let check = || -> std::result::Result<(), model_validation::ValidationError> {
+                        for item in value.iter() {
+                            item.validate().map_err(|err| err.within("parts"))?;
+                        }
+                        Ok(())
+                    };
+                    check().context(ValidationError)?;
+                }
+            }
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn test_constant_parameters() {
+        let cg = CodeGen::new(Config {
+            input_files: vec![PathBuf::from(CONSTANTS_SPEC)],
+            ..Default::default()
+        })
+        .unwrap();
+        let operations = cg.create_operations().unwrap();
+        let code = operations.parts.iter().map(|part| part.content.to_string()).collect::<String>();
+        // the required parameters with a single value are not arguments, an optional one is
+        assert!(code.contains(
+            &quote! {
+                pub async fn get(
+                    operation_config: &crate::OperationConfig,
+                    widget_name: &str,
+                    filter: Option<&str>
+                ) -> std::result::Result<String, get::Error>
+            }
+            .to_string()
+        ));
+        // their values are sent in the path, query and headers
+        assert!(code.contains(&quote! { "{}/widgets/{}/colors/{}", operation_config.base_path(), widget_name, "blue" }.to_string()));
+        assert!(code.contains(
+            &quote! {
+                req.query("format", "json");
+                req.header("x-ms-mode", "fast");
+            }
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn test_secret_debug() {
+        let cg = CodeGen::new(Config {
+            input_files: vec![PathBuf::from(SECRETS_SPEC)],
+            ..Default::default()
+        })
+        .unwrap();
+        let models = cg.create_models().unwrap().to_file().to_string();
+        // `Debug` is implemented instead of derived, and redacts the secret fields
+        assert!(models.contains(&quote! { #[derive(Clone, PartialEq, Serialize, Deserialize)] pub struct Credentials }.to_string()));
+        let debug = quote! {
+            impl std::fmt::Debug for Credentials {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.debug_struct("Credentials")
+                        .field("password", &"<redacted>")
+                        .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+                        .field("type", &self.r#type)
+                        .finish()
+                }
+            }
+        };
+        assert!(models.contains(&debug.to_string()));
+    }
+
+    #[test]
+    fn test_model_attributes() {
+        let mut schema_model_attributes = HashMap::new();
+        schema_model_attributes.insert(
+            "Point".to_owned(),
+            ModelAttributes {
+                derives: vec!["Copy".to_owned()],
+                attributes: vec!["serde(deny_unknown_fields)".to_owned()],
+            },
+        );
+        let cg = CodeGen::new(Config {
+            input_files: vec![PathBuf::from(MODEL_ATTRIBUTES_SPEC)],
+            model_attributes: ModelAttributes {
+                derives: vec!["Eq".to_owned(), "Hash".to_owned(), "Default".to_owned()],
+                attributes: vec!["non_exhaustive".to_owned()],
+            },
+            schema_model_attributes,
+            ..Default::default()
+        })
+        .unwrap();
+        let models = cg.create_models().unwrap().to_file().to_string();
+        // `Eq` and `Hash` are not derived for a float field
+        assert!(models.contains(
+            &quote! {
+                #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+                #[non_exhaustive]
+                pub struct Measurement
+            }
+            .to_string()
+        ));
+        // the attributes of a definition follow those of all the models
+        assert!(models.contains(
+            &quote! {
+                #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Eq, Hash, Copy)]
+                #[non_exhaustive]
+                #[serde(deny_unknown_fields)]
+                pub struct Point
+            }
+            .to_string()
+        ));
+        // `Default` is implemented with the constructor when it has parameters
+        assert!(models.contains(
+            &quote! {
+                impl Default for Point {
+                    fn default() -> Self {
+                        Self::new(Default::default())
+                    }
+                }
+            }
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn test_formatting() {
+        let config = Config {
+            input_files: vec![PathBuf::from(CONSTANTS_SPEC)],
+            output_folder: PathBuf::from("out"),
+            ..Default::default()
+        };
+        let generation = crate::generate(&config).unwrap();
+        let operations = generation.file("operations.rs").unwrap();
+        assert!(operations.starts_with("//! generated by AutoRust"));
+        assert!(operations.contains(
+            "    pub async fn get(
+        operation_config: &crate::OperationConfig,
+        widget_name: &str,
+        filter: Option<&str>,
+    ) -> std::result::Result<String, get::Error> {
+"
+        ));
+
+        // the unformatted code is on a single line
+        let generation = crate::generate(&Config {
+            unformatted: true,
+            ..config
+        })
+        .unwrap();
+        let operations = generation.file("operations.rs").unwrap();
+        assert!(!operations.contains('\n'));
+        assert!(operations.contains(
+            &quote! { pub async fn get(operation_config: &crate::OperationConfig, widget_name: &str, filter: Option<&str>) }.to_string()
+        ));
+    }
+
+    #[test]
+    fn test_split_files() {
+        let generation = crate::generate(&Config {
+            input_files: vec![PathBuf::from(GROUPS_SPEC)],
+            output_folder: PathBuf::from("out"),
+            split_files: true,
+            ..Default::default()
+        })
+        .unwrap();
+        // the models of a specification file are in a file by its name, which the `mod.rs` reexports
+        let models = generation.file("models/mod.rs").unwrap();
+        assert!(models.ends_with("mod groups;\npub use groups::*;\n"));
+        assert!(generation.file("models/groups.rs").unwrap().contains("pub struct Widget {"));
+        // an operation group is a public module in its own file, and the operations without a group stay in the `mod.rs`
+        let operations = generation.file("operations/mod.rs").unwrap();
+        assert!(operations.contains("pub async fn get_status("));
+        assert!(operations.ends_with("pub mod gadgets;\npub mod widgets;\n"));
+        assert!(generation.file("operations/widgets.rs").unwrap().contains("pub async fn get("));
+        assert!(generation.file("operations/gadgets.rs").unwrap().contains("pub async fn list("));
+        assert!(generation.file("models.rs").is_none());
+        assert!(generation.file("operations.rs").is_none());
+    }
+
+    #[test]
+    fn test_blocking() {
+        let config = Config {
+            input_files: vec![PathBuf::from(BLOCKING_SPEC)],
+            output_folder: PathBuf::from("out"),
+            api_version: Some("2021-01-01".to_owned()),
+            target: Target::Standalone,
+            blocking: true,
+            ..Default::default()
+        };
+        let generation = crate::generate(&config).unwrap();
+        let operations = generation.file("operations.rs").unwrap();
+        assert!(operations.contains("pub fn blocking("));
+        assert!(operations.contains("operation_config.send_blocking(req)"));
+        assert!(operations.contains("pub fn wait_for_completion_blocking("));
+        assert!(operations.contains("autorust_runtime::block_on("));
+        assert!(operations.contains("wait_for_completion(operation_config, widget_name, widget)"));
+        // the blocking operations are configured with the synchronous HTTP client of the `ureq` feature of the runtime
+        let mod_rs = generation.file("mod.rs").unwrap();
+        assert!(mod_rs.contains("pub fn blocking_config() -> autorust_runtime::OperationConfigBuilder {"));
+        assert!(mod_rs.contains("config(std::sync::Arc::new(autorust_runtime::ureq::Agent::new()))"));
+        let feature_mod_names = vec![("package-2021".to_owned(), "package_2021".to_owned())];
+        let cargo_toml = crate::cargo_toml::create_content(
+            "blocking",
+            &feature_mod_names,
+            &HashMap::new(),
+            generation.dependencies(),
+            Target::Standalone,
+            "../runtime",
+            false,
+            true,
+        );
+        assert!(cargo_toml.contains(r#"autorust_runtime = { path = "../runtime", features = ["reqwest", "tokio", "ureq"] }"#));
+
+        // without the blocking operations, there is neither the `ureq` feature nor the `blocking_config` function
+        let generation = crate::generate(&Config { blocking: false, ..config }).unwrap();
+        assert!(!generation.file("operations.rs").unwrap().contains("blocking"));
+        assert!(!generation.file("mod.rs").unwrap().contains("blocking_config"));
+        assert!(!Target::Standalone.dependencies("../runtime", false).contains("ureq"));
+
+        // the `azure_core` target has no synchronous HTTP client
+        let config = Config {
+            input_files: vec![PathBuf::from(BLOCKING_SPEC)],
+            blocking: true,
+            ..Default::default()
+        };
+        assert!(matches!(CodeGen::new(config), Err(Error::BlockingTargetError { .. })));
+    }
+
+    #[test]
+    fn test_flatten() {
+        let cg = CodeGen::new(Config {
+            input_files: vec![PathBuf::from(FLATTEN_SPEC)],
+            ..Default::default()
+        })
+        .unwrap();
+        let models = cg.create_models().unwrap().to_file().to_string();
+        // the fields of the flattened properties are named by their `x-ms-client-name`, also next to a `$ref`
+        let widget = quote! {
+            #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+            #[serde(from = "widget::Wire", into = "widget::Wire")]
+            pub struct Widget {
+                pub name: Option<String>,
+                pub creator: Option<User>,
+                pub colour: Option<String>,
+                pub size: i32,
+            }
+        };
+        assert!(models.contains(&widget.to_string()));
+        // the wire model has the shape that is serialized
+        let wire = quote! {
+            pub struct Wire {
+                #[serde(skip_serializing_if = "Option::is_none")]
+                pub name: Option<String>,
+                #[serde(rename = "owner", skip_serializing_if = "Option::is_none")]
+                pub creator: Option<User>,
+                pub properties: WidgetProperties,
+            }
+        };
+        assert!(models.contains(&wire.to_string()));
+        let conversions = quote! {
+            impl From<Wire> for Widget {
+                fn from(wire: Wire) -> Self {
+                    let Wire { name, creator, properties } = wire;
+                    let (colour, size,) = (properties.colour, properties.size,);
+                    Self { name, creator, colour, size }
+                }
+            }
+            impl From<Widget> for Wire {
+                fn from(value: Widget) -> Self {
+                    let Widget { name, creator, colour, size } = value;
+                    let properties = WidgetProperties { colour, size };
+                    Self { name, creator, properties }
+                }
+            }
+        };
+        assert!(models.contains(&conversions.to_string()));
+    }
+
+    #[test]
+    fn test_same_names() {
+        let cg = CodeGen::new(Config {
+            input_files: SAME_NAMES_SPECS.iter().map(PathBuf::from).collect(),
+            ..Default::default()
+        })
+        .unwrap();
+        let mut type_names: Vec<_> = cg
+            .type_names
+            .iter()
+            .map(|(ref_key, type_name)| {
+                let file_name = ref_key.file_path.file_name().and_then(OsStr::to_str).unwrap();
+                (file_name, ref_key.name.as_str(), type_name.as_str())
+            })
+            .collect();
+        type_names.sort();
+        // the error details are written the same, but their targets differ
+        assert_eq!(
+            type_names,
+            [
+                ("a.json", "ErrorDetail", "ErrorDetail"),
+                ("a.json", "Shared", "Shared"),
+                ("a.json", "Target", "Target"),
+                ("b.json", "ErrorDetail", "BErrorDetail"),
+                ("b.json", "Shared", "Shared"),
+                ("b.json", "Target", "BTarget"),
+                ("parameters.json", "Widget", "Widget"),
+            ]
+        );
+
+        let models = cg.create_models().unwrap().to_file().to_string();
+        for (type_name, target_type_name) in &[
+            (quote! { ErrorDetail }, quote! { Target }),
+            (quote! { BErrorDetail }, quote! { BTarget }),
+        ] {
+            let error_detail = quote! {
+                pub struct #type_name {
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    pub message: Option<String>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    pub target: Option<#target_type_name>,
+                }
+            };
+            assert!(models.contains(&error_detail.to_string()));
+        }
+        assert_eq!(models.matches("pub struct Shared {").count(), 1);
+
+        // the schema of a parameter from another file is referenced from that file
+        let operations = cg.create_operations().unwrap();
+        let code = operations.parts.iter().map(|part| part.content.to_string()).collect::<String>();
+        assert!(code.contains(&quote! { widget_name: &str, widget: &Widget }.to_string()));
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let config = Config {
+            input_files: vec![PathBuf::from(DUPLICATE_NAMES_SPEC)],
+            output_folder: PathBuf::from("out"),
+            unformatted: true,
+            ..Default::default()
+        };
+        let generation = crate::generate(&config).unwrap();
+        // an operation named like another one in its group is renamed and reported
+        assert_eq!(
+            generation.renamed_functions(),
+            [format!(
+                "{}#Certificates_List_All to certificates::list_all_2",
+                DUPLICATE_NAMES_SPEC
+            )]
+        );
+        // parameters named like other parameters or the configuration are renamed
+        let operations = generation.file("operations.rs").unwrap();
+        assert!(operations.contains(
+            &quote! {
+                pub async fn delete(
+                    operation_config: &crate::OperationConfig,
+                    certificate_name: &str,
+                    certificate_name_2: Option<&str>,
+                    operation_config_2: Option<&str>
+                )
+            }
+            .to_string()
+        ));
+        assert!(operations.contains(
+            &quote! {
+                if let Some(certificate_name_2) = certificate_name_2 {
+                    req.query("certificate.name", certificate_name_2);
+                }
+                if let Some(operation_config_2) = operation_config_2 {
+                    req.header("operationConfig", operation_config_2);
+                }
+            }
+            .to_string()
+        ));
     }
 }
@@ -0,0 +1,167 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Creates the `#[doc]` attributes for a description from the spec, one per line.
+/// Nothing is created for an empty description.
+pub fn create_doc(text: &str) -> TokenStream {
+    let text = sanitize(text);
+    let lines = text
+        .lines()
+        .map(|line| if line.is_empty() { String::new() } else { format!(" {}", line) });
+    quote! { #(#[doc = #lines])* }
+}
+
+/// Sanitizes the markdown of a description from the spec, so that rustdoc renders it as text.
+/// Code blocks are not run as doctests and brackets, angle brackets and URLs are not treated as links or HTML.
+pub fn sanitize(text: &str) -> String {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut lines = Vec::new();
+    let mut in_code_block = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            lines.push(if in_code_block { "```".to_owned() } else { "```text".to_owned() });
+            in_code_block = !in_code_block;
+        } else if in_code_block {
+            lines.push(line.trim_end().to_owned());
+        } else {
+            // an indented line would start a code block, which is run as a doctest
+            lines.push(escape_line(trimmed));
+        }
+    }
+    if in_code_block {
+        lines.push("```".to_owned());
+    }
+    let first = lines.iter().position(|line| !line.is_empty()).unwrap_or(lines.len());
+    let last = lines.iter().rposition(|line| !line.is_empty()).map_or(first, |i| i + 1);
+    lines[first..last].join("\n")
+}
+
+fn escape_line(line: &str) -> String {
+    let mut txt = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(ch) = rest.chars().next() {
+        let (escaped, len) = match ch {
+            '`' => code_span(rest),
+            '[' => link(rest),
+            ']' => ("\\]".to_owned(), 1),
+            '<' => autolink(rest),
+            'h' if rest.starts_with("http://") || rest.starts_with("https://") => bare_url(rest),
+            _ => (ch.to_string(), ch.len_utf8()),
+        };
+        txt.push_str(&escaped);
+        rest = &rest[len..];
+    }
+    txt
+}
+
+/// Keeps a code span as it is.
+fn code_span(text: &str) -> (String, usize) {
+    let ticks = text.len() - text.trim_start_matches('`').len();
+    let delimiter = &text[..ticks];
+    match text[ticks..].find(delimiter) {
+        Some(end) => {
+            let len = ticks + end + ticks;
+            (text[..len].to_owned(), len)
+        }
+        None => (delimiter.to_owned(), ticks),
+    }
+}
+
+/// Keeps an inline link, such as `[text](url)`, and escapes other brackets that could be taken for intra-doc links.
+fn link(text: &str) -> (String, usize) {
+    if let Some(close) = text.find("](") {
+        if !text[1..close].contains('[') {
+            if let Some(end) = text[close..].find(')') {
+                let len = close + end + 1;
+                return (format!("[{}{}", escape_line(&text[1..close]), &text[close..len]), len);
+            }
+        }
+    }
+    ("\\[".to_owned(), 1)
+}
+
+/// Keeps an autolink, such as `<https://example.com>`, and escapes other angle brackets that could be taken for HTML.
+fn autolink(text: &str) -> (String, usize) {
+    if text.starts_with("<http://") || text.starts_with("<https://") {
+        if let Some(end) = text.find('>') {
+            if !text[..end].contains(char::is_whitespace) {
+                return (text[..=end].to_owned(), end + 1);
+            }
+        }
+    }
+    ("&lt;".to_owned(), 1)
+}
+
+/// Wraps a bare URL in angle brackets, so that it is a link.
+fn bare_url(text: &str) -> (String, usize) {
+    let end = text
+        .find(|ch: char| ch.is_whitespace() || ch == '<' || ch == '>')
+        .unwrap_or(text.len());
+    let mut url = &text[..end];
+    let trailing: &[char] = if url.contains('(') {
+        &['.', ',', ';', ':', '\'', '"']
+    } else {
+        &['.', ',', ';', ':', '\'', '"', ')']
+    };
+    url = url.trim_end_matches(trailing);
+    (format!("<{}>", url), url.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_text() {
+        assert_eq!(sanitize("  The name of the resource.  "), "The name of the resource.");
+    }
+
+    #[test]
+    fn test_code_block_is_text() {
+        assert_eq!(
+            sanitize("Example:\n```json\n{ \"a\": 1 }\n```"),
+            "Example:\n```text\n{ \"a\": 1 }\n```"
+        );
+        assert_eq!(sanitize("```\nunclosed"), "```text\nunclosed\n```");
+    }
+
+    #[test]
+    fn test_indented_lines() {
+        assert_eq!(sanitize("Values:\n\n    fn main() {}"), "Values:\n\nfn main() {}");
+    }
+
+    #[test]
+    fn test_brackets() {
+        assert_eq!(
+            sanitize("Possible values [Basic, Standard]"),
+            "Possible values \\[Basic, Standard\\]"
+        );
+        assert_eq!(
+            sanitize("See [the docs](https://aka.ms/docs)."),
+            "See [the docs](https://aka.ms/docs)."
+        );
+        assert_eq!(sanitize("`[a]` stays"), "`[a]` stays");
+    }
+
+    #[test]
+    fn test_angle_brackets() {
+        assert_eq!(sanitize("A List<string> value"), "A List&lt;string> value");
+        assert_eq!(sanitize("See <https://aka.ms/docs>"), "See <https://aka.ms/docs>");
+    }
+
+    #[test]
+    fn test_bare_url() {
+        assert_eq!(sanitize("See https://aka.ms/docs."), "See <https://aka.ms/docs>.");
+        assert_eq!(sanitize("(see https://aka.ms/docs)"), "(see <https://aka.ms/docs>)");
+    }
+
+    #[test]
+    fn test_create_doc() {
+        assert_eq!(create_doc("").to_string(), "");
+        assert_eq!(
+            create_doc("Line one\nline two").to_string(),
+            quote! { #[doc = " Line one"] #[doc = " line two"] }.to_string()
+        );
+    }
+}
@@ -2,6 +2,7 @@ use heck::CamelCase;
 use proc_macro2::TokenStream;
 use quote::ToTokens;
 use snafu::{ResultExt, Snafu};
+use std::collections::HashSet;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 #[derive(Debug, Snafu)]
@@ -16,42 +17,112 @@ pub trait CamelCaseIdent: ToOwned {
 
 impl CamelCaseIdent for str {
     fn to_camel_case_ident(&self) -> Result<TokenStream> {
-        let mut txt = replace_chars_with_unicode_names(self);
+        let mut txt = to_ascii(self);
+        txt = replace_symbols_with_names(&txt, |name| format!("_{}_", name));
         txt = replace_chars_with_underscore(&txt);
-        txt = if starts_with_number(&txt) {
-            prefix_with_underscore_if_starts_with_number(&txt)
-        } else {
-            txt.to_camel_case()
-        };
+        if !starts_with_number(&txt) {
+            txt = txt.to_camel_case();
+        }
+        txt = prefix_with_underscore_if_starts_with_number(&txt);
+        if txt.is_empty() {
+            txt = "Empty".to_owned();
+        }
+        txt = suffix_with_underscore_keywords(&txt);
         let idt = syn::parse_str::<syn::Ident>(&txt).context(ParseIdentError { text: txt.to_owned() })?;
         Ok(idt.into_token_stream())
     }
 }
 
 pub fn ident(text: &str) -> Result<TokenStream> {
-    let mut txt = replace_chars_with_underscore(text);
+    let mut txt = to_ascii(text);
     txt = remove_spaces(&txt);
+    txt = replace_symbols_with_names(&txt, |name| format!("_{}_", name.to_lowercase()));
+    txt = replace_chars_with_underscore(&txt);
     txt = prefix_with_underscore_if_starts_with_number(&txt);
-    txt = prefix_with_underscore_keywords(&txt);
+    txt = match txt.as_str() {
+        "" => "empty".to_owned(),
+        "_" => "underscore".to_owned(),
+        _ => txt,
+    };
+    txt = raw_keywords(&txt);
     let idt = syn::parse_str::<syn::Ident>(&txt).context(ParseIdentError { text: txt.to_owned() })?;
     Ok(idt.into_token_stream())
 }
 
+/// The identifiers used in a scope, such as the fields of a struct or the variants of an enum.
+/// Distinct names may map to the same identifier, so a number is appended to the ones that follow.
+#[derive(Debug, Default)]
+pub struct UniqueIdents {
+    idents: HashSet<String>,
+}
+
+impl UniqueIdents {
+    /// Adds an identifier to the scope, returning it with a number appended if it was already used.
+    /// A number is appended to a `CamelCase` identifier directly and to others after an underscore.
+    pub fn insert(&mut self, idt: TokenStream) -> Result<TokenStream> {
+        let text = idt.to_string();
+        if self.idents.insert(text.clone()) {
+            return Ok(idt);
+        }
+        let text = text.trim_start_matches("r#");
+        let separator = if text.starts_with(char::is_uppercase) { "" } else { "_" };
+        let mut n = 2;
+        loop {
+            let txt = format!("{}{}{}", text, separator, n);
+            if self.idents.insert(txt.clone()) {
+                let idt = syn::parse_str::<syn::Ident>(&txt).context(ParseIdentError { text: txt })?;
+                return Ok(idt.into_token_stream());
+            }
+            n += 1;
+        }
+    }
+}
+
 fn remove_spaces(text: &str) -> String {
     text.replace(" ", "")
 }
 
+/// Transliterate to ASCII, which also normalizes Unicode punctuation, such as an en dash to a hyphen
+fn to_ascii(text: &str) -> String {
+    deunicode::deunicode_with_tofu(text, "_")
+}
+
+/// Replace any character that can not be part of an identifier with an underscore
 fn replace_chars_with_underscore(text: &str) -> String {
-    let mut txt = text.replace(".", "_");
-    txt = txt.replace(",", "_");
-    txt = txt.replace("-", "_");
-    txt = txt.replace("/", "_");
-    txt
+    text.chars().map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' }).collect()
 }
 
 /// Replace some special charaters with their unicode names
-fn replace_chars_with_unicode_names(text: &str) -> String {
-    text.replace("*", "Asterisk")
+fn replace_symbols_with_names(text: &str, format_name: impl Fn(&str) -> String) -> String {
+    let mut txt = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match symbol_name(ch) {
+            Some(name) => txt.push_str(&format_name(name)),
+            None => txt.push(ch),
+        }
+    }
+    txt
+}
+
+fn symbol_name(ch: char) -> Option<&'static str> {
+    Some(match ch {
+        '*' => "Asterisk",
+        ':' => "Colon",
+        '+' => "Plus",
+        '@' => "At",
+        '#' => "Hash",
+        '%' => "Percent",
+        '&' => "Ampersand",
+        '=' => "Equals",
+        '<' => "LessThan",
+        '>' => "GreaterThan",
+        '!' => "Exclamation",
+        '?' => "Question",
+        '~' => "Tilde",
+        '^' => "Caret",
+        '|' => "Pipe",
+        _ => return None,
+    })
 }
 
 fn starts_with_number(text: &str) -> bool {
@@ -72,21 +143,38 @@ fn prefix_with_underscore_if_starts_with_number(text: &str) -> String {
     }
 }
 
-fn prefix_with_underscore_keywords(text: &str) -> String {
-    if is_keyword(&text) {
+/// Use a raw identifier for a keyword, or append an underscore to the few that can not be raw
+fn raw_keywords(text: &str) -> String {
+    if !is_keyword(text) {
+        text.to_owned()
+    } else if can_be_raw(text) {
+        format!("r#{}", text)
+    } else {
+        format!("{}_", text)
+    }
+}
+
+fn suffix_with_underscore_keywords(text: &str) -> String {
+    if is_keyword(text) {
         format!("{}_", text)
     } else {
         text.to_owned()
     }
 }
 
+fn can_be_raw(word: &str) -> bool {
+    !matches!(word, "crate" | "self" | "Self" | "super")
+}
+
 fn is_keyword(word: &str) -> bool {
     matches!(
         word,
-        // https://doc.rust-lang.org/grammar.html#keywords
+        // https://doc.rust-lang.org/reference/keywords.html
         "abstract"
             | "alignof"
             | "as"
+            | "async"
+            | "await"
             | "become"
             | "box"
             | "break"
@@ -94,6 +182,7 @@ fn is_keyword(word: &str) -> bool {
             | "continue"
             | "crate"
             | "do"
+            | "dyn"
             | "else"
             | "enum"
             | "extern"
@@ -127,6 +216,7 @@ fn is_keyword(word: &str) -> bool {
             | "super"
             | "trait"
             | "true"
+            | "try"
             | "type"
             | "typeof"
             | "unsafe"
@@ -143,6 +233,7 @@ fn is_keyword(word: &str) -> bool {
 mod tests {
     use super::*;
     use heck::SnakeCase;
+    use proptest::prelude::*;
 
     #[test]
     fn test_odata_next_link() -> Result<()> {
@@ -220,4 +311,117 @@ mod tests {
         assert_eq!("1.0".to_camel_case_ident()?.to_string(), "_1_0");
         Ok(())
     }
+
+    #[test]
+    fn test_unique_idents_standard_lrs() -> Result<()> {
+        let mut idents = UniqueIdents::default();
+        assert_eq!(idents.insert("Standard_LRS".to_camel_case_ident()?)?.to_string(), "StandardLrs");
+        assert_eq!(idents.insert("StandardLRS".to_camel_case_ident()?)?.to_string(), "StandardLrs2");
+        assert_eq!(idents.insert("standard-lrs".to_camel_case_ident()?)?.to_string(), "StandardLrs3");
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_idents_foo_bar() -> Result<()> {
+        let mut idents = UniqueIdents::default();
+        assert_eq!(idents.insert(ident("foo-bar")?)?.to_string(), "foo_bar");
+        assert_eq!(idents.insert(ident("foo_bar")?)?.to_string(), "foo_bar_2");
+        assert_eq!(idents.insert(ident("foo_bar_2")?)?.to_string(), "foo_bar_2_2");
+        Ok(())
+    }
+
+    #[test]
+    fn test_10minutely() -> Result<()> {
+        assert_eq!("10minutely".to_camel_case_ident()?.to_string(), "_10minutely");
+        Ok(())
+    }
+
+    #[test]
+    fn test_ref_colon_machine() -> Result<()> {
+        assert_eq!("Ref:machine".to_camel_case_ident()?.to_string(), "RefColonMachine");
+        assert_eq!(ident("Ref:machine")?.to_string(), "Ref_colon_machine");
+        Ok(())
+    }
+
+    #[test]
+    fn test_symbols() -> Result<()> {
+        assert_eq!("a+b".to_camel_case_ident()?.to_string(), "APlusB");
+        assert_eq!("@odata.type".to_camel_case_ident()?.to_string(), "AtOdataType");
+        assert_eq!("C#".to_camel_case_ident()?.to_string(), "CHash");
+        assert_eq!("100%".to_camel_case_ident()?.to_string(), "_100_Percent_");
+        Ok(())
+    }
+
+    #[test]
+    fn test_en_dash() -> Result<()> {
+        assert_eq!("a\u{2013}b".to_camel_case_ident()?.to_string(), "AB");
+        assert_eq!(ident("a\u{2013}b")?.to_string(), "a_b");
+        Ok(())
+    }
+
+    #[test]
+    fn test_transliterate() -> Result<()> {
+        assert_eq!("Größe".to_camel_case_ident()?.to_string(), "Grosse");
+        assert_eq!(ident("café")?.to_string(), "cafe");
+        Ok(())
+    }
+
+    #[test]
+    fn test_keywords() -> Result<()> {
+        assert_eq!(ident("type")?.to_string(), "r#type");
+        assert_eq!(ident("async")?.to_string(), "r#async");
+        assert_eq!(ident("self")?.to_string(), "self_");
+        assert_eq!(ident("Self")?.to_string(), "Self_");
+        assert_eq!("self".to_camel_case_ident()?.to_string(), "Self_");
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty() -> Result<()> {
+        assert_eq!("".to_camel_case_ident()?.to_string(), "Empty");
+        assert_eq!("_".to_camel_case_ident()?.to_string(), "Empty");
+        assert_eq!(ident("")?.to_string(), "empty");
+        assert_eq!(ident("_")?.to_string(), "underscore");
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_idents_raw() -> Result<()> {
+        let mut idents = UniqueIdents::default();
+        assert_eq!(idents.insert(ident("type")?)?.to_string(), "r#type");
+        assert_eq!(idents.insert(ident("type")?)?.to_string(), "type_2");
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn prop_ident_is_total(text in any::<String>()) {
+            let idt = ident(&text).unwrap();
+            prop_assert!(syn::parse_str::<syn::Ident>(&idt.to_string()).is_ok());
+        }
+
+        #[test]
+        fn prop_camel_case_ident_is_total(text in any::<String>()) {
+            let idt = text.to_camel_case_ident().unwrap();
+            prop_assert!(syn::parse_str::<syn::Ident>(&idt.to_string()).is_ok());
+        }
+
+        #[test]
+        fn prop_ident_is_ascii(text in "\\PC*") {
+            prop_assert!(ident(&text).unwrap().to_string().is_ascii());
+            prop_assert!(text.to_camel_case_ident().unwrap().to_string().is_ascii());
+        }
+
+        #[test]
+        fn prop_ident_keeps_snake_case(text in "[a-z][a-z0-9_]*") {
+            prop_assume!(!is_keyword(&text));
+            prop_assert_eq!(ident(&text).unwrap().to_string(), text);
+        }
+
+        #[test]
+        fn prop_camel_case_ident_keeps_camel_case(text in "[A-Z][a-z0-9]*") {
+            prop_assume!(!is_keyword(&text));
+            prop_assert_eq!(text.to_camel_case_ident().unwrap().to_string(), text);
+        }
+    }
 }
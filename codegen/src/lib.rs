@@ -1,15 +1,21 @@
 pub mod cargo_toml;
 mod codegen;
 pub mod config_parser;
+mod doc_comment;
 pub mod identifier;
 pub mod lib_rs;
+pub mod manifest;
 pub mod path;
+mod resource_id;
 pub mod spec;
 mod status_codes;
+mod target;
 
 pub use self::{
-    codegen::{create_mod, CodeGen},
-    spec::{OperationVerb, ResolvedSchema, Spec},
+    codegen::{create_mod, CodeGen, ModuleCode, ModulePart},
+    manifest::{Generation, Report},
+    spec::{OperationVerb, RefKey, ResolvedSchema, Spec},
+    target::Target,
 };
 
 use config_parser::Configuration;
@@ -17,7 +23,7 @@ use proc_macro2::TokenStream;
 use snafu::{OptionExt, ResultExt, Snafu};
 
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fs::{self, File},
     io::prelude::*,
     path::{Path, PathBuf},
@@ -27,16 +33,16 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
-    #[snafu(display("Could not create output directory {}: {}", directory.display(), source))]
-    CreateOutputDirectoryError {
-        directory: PathBuf,
-        source: std::io::Error,
-    },
     #[snafu(display("Could not create file {}: {}", file.display(), source))]
     CreateFileError {
         file: PathBuf,
         source: std::io::Error,
     },
+    #[snafu(display("Could not parse the code generated for file {}: {}", file.display(), source))]
+    ParseGeneratedCodeError {
+        file: PathBuf,
+        source: syn::Error,
+    },
     #[snafu(display("Could not write file {}: {}", file.display(), source))]
     WriteFileError {
         file: PathBuf,
@@ -53,6 +59,9 @@ pub enum Error {
     CreateOperationsError {
         source: codegen::Error,
     },
+    ManifestError {
+        source: manifest::Error,
+    },
     PathError {
         source: path::Error,
     },
@@ -70,46 +79,179 @@ pub struct PropertyName {
     pub property_name: String,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// An operation whose function has been renamed, as another operation in its operation group has a function with the same name.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RenamedFunction {
+    pub file_path: PathBuf,
+    /// The `operationId` of the operation, or its method and path when it has none.
+    pub operation: String,
+    /// The path of the function in the operations module.
+    pub function_path: String,
+}
+
+/// The `type` and `format` of a schema, such as `string` and `uuid`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TypeFormat {
+    pub type_: String,
+    pub format: String,
+}
+
+/// Derives and attributes to add to the generated models.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModelAttributes {
+    /// Traits to derive, as Rust paths such as `Eq`, `Hash`, `Default` or `schemars::JsonSchema`.
+    /// `Eq`, `Ord`, `PartialOrd` and `Hash` are only derived when the types of all the fields implement them,
+    /// so not `Eq` when there are floats, nor `Ord` and `PartialOrd` when there are floats or untyped JSON values.
+    /// `Default` is implemented by calling the constructor with default values, when all of its parameters implement it.
+    pub derives: Vec<String>,
+    /// Attributes without the `#[]`, such as `non_exhaustive`.
+    pub attributes: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Config {
     pub input_files: Vec<PathBuf>,
     pub output_folder: PathBuf,
     pub api_version: Option<String>,
     pub box_properties: HashSet<PropertyName>,
+    /// Also create an `Input` struct for models with read-only properties, containing only the properties that may be sent,
+    /// and for the models whose fields have `Input` structs. The request bodies of the operations are `Input` structs.
+    pub input_models: bool,
+    /// Create `validate` methods on models and check the parameter constraints before sending a request.
+    pub validation: bool,
+    /// Create typed ARM resource IDs for the resource paths, use them for `format: arm-id` fields,
+    /// and create functions that take the path parameters of an operation from a resource ID.
+    pub resource_ids: bool,
+    /// Types to use instead of creating models for schema definitions, as Rust paths such as `azure_core::SystemData`.
+    /// The file path of a key is the path of the file as it is referenced.
+    pub schema_types: HashMap<RefKey, String>,
+    /// Types to use for schemas with a `type` and `format`, as Rust paths such as `uuid::Uuid`.
+    pub format_types: HashMap<TypeFormat, String>,
+    /// Derives and attributes for all the models.
+    pub model_attributes: ModelAttributes,
+    /// Derives and attributes for the models of schema definitions, by the name of the definition.
+    pub schema_model_attributes: HashMap<String, ModelAttributes>,
+    /// Write the generated code on a single line, as the tokens are, instead of pretty-printing it.
+    pub unformatted: bool,
+    /// Write the models in a file per specification file, and the operations in a file per operation group,
+    /// in the `models` and `operations` folders, instead of in a single `models.rs` and `operations.rs`.
+    pub split_files: bool,
+    /// The cargo feature of the generated module. When it is set, each operation group is gated by a feature of its own,
+    /// named after this one and the group, and each model by the features of the operation groups that use it.
+    pub feature_name: Option<String>,
+    /// Only create the models that are reachable from the operations, through their parameters and responses,
+    /// instead of all of the definitions in the input files.
+    pub prune_models: bool,
+    /// Also create blocking variants of the operations, for programs without an async runtime.
+    /// They share the requests and the responses with the async operations, and need a synchronous HTTP client.
+    pub blocking: bool,
+    /// Trace the operations in spans of the `tracing` crate, when the `tracing` feature of the runtime is enabled.
+    pub tracing: bool,
+    /// The runtime that the `config` function of the `mod.rs` with the `api_version` creates the configuration for.
+    pub target: Target,
+}
+
+/// Generates the code and writes the files that have changed, see `Generation::write`.
+pub fn run(config: Config) -> Result<Report> {
+    let generation = generate(&config)?;
+    generation.write().context(ManifestError)
 }
 
-pub fn run(config: Config) -> Result<()> {
-    let directory = &config.output_folder;
-    fs::create_dir_all(directory).context(CreateOutputDirectoryError { directory })?;
+/// Generates the code for the output folder, without writing it.
+pub fn generate(config: &Config) -> Result<Generation> {
     let cg = &CodeGen::new(config.clone()).context(CodeGenNewError)?;
+    let mut generation = Generation::new(&config.output_folder);
+    for input in cg.spec.docs().keys() {
+        generation.add_input(input);
+    }
+    for feature in cg.group_features() {
+        generation.add_feature(feature);
+    }
+    if config.validation {
+        // the patterns are compiled once, when they are first checked
+        generation.add_dependency("regex");
+        generation.add_dependency("lazy_static");
+    }
+    for ref_key in cg.pruned_schemas().context(CreateModelsError { config: config.clone() })? {
+        generation.add_pruned_model(format!("{}#{}", ref_key.file_path.display(), ref_key.name));
+    }
+    for (ref_key, type_name) in cg.renamed_schemas() {
+        generation.add_renamed_model(format!("{}#{} to {}", ref_key.file_path.display(), ref_key.name, type_name));
+    }
 
     // create models from schemas
     let models = cg.create_models().context(CreateModelsError { config: config.clone() })?;
-    let models_path = path::join(&config.output_folder, "models.rs").context(PathError)?;
-    write_file(&models_path, &models)?;
+    add_module(&mut generation, "models", &models, config)?;
+    if cg.has_integer_enums() {
+        generation.add_dependency("serde_repr");
+    }
+    for property in cg.unflattened_properties() {
+        generation.add_unflattened_property(format!(
+            "{}#{}.{}",
+            property.file_path.display(),
+            property.schema_name,
+            property.property_name
+        ));
+    }
 
     // create api client from operations
     let operations = cg.create_operations().context(CreateOperationsError)?;
-    let operations_path = path::join(&config.output_folder, "operations.rs").context(PathError)?;
-    write_file(&operations_path, &operations)?;
+    add_module(&mut generation, "operations", &operations, config)?;
+    for function in cg.renamed_functions() {
+        generation.add_renamed_function(format!(
+            "{}#{} to {}",
+            function.file_path.display(),
+            function.operation,
+            function.function_path
+        ));
+    }
 
     if let Some(api_version) = &config.api_version {
-        let operations = create_mod(api_version);
-        let operations_path = path::join(&config.output_folder, "mod.rs").context(PathError)?;
-        write_file(&operations_path, &operations)?;
+        let operations = create_mod(api_version, config.target, cg.spec.base_path(), config.blocking);
+        add_file(&mut generation, "mod.rs", &operations, config.unformatted)?;
+    }
+    Ok(generation)
+}
+
+/// Adds a module as a single file, or as a folder with a file for each of its parts.
+fn add_module(generation: &mut Generation, name: &str, module: &ModuleCode, config: &Config) -> Result<()> {
+    if config.split_files {
+        let (mod_file, files) = module.to_files();
+        add_file(generation, &format!("{}/mod.rs", name), &mod_file, config.unformatted)?;
+        for (file_name, file) in files {
+            let file_name = file_name.trim_start_matches("r#");
+            add_file(generation, &format!("{}/{}.rs", name, file_name), &file, config.unformatted)?;
+        }
+        Ok(())
+    } else {
+        add_file(generation, &format!("{}.rs", name), &module.to_file(), config.unformatted)
     }
+}
+
+fn add_file(generation: &mut Generation, file: &str, tokens: &TokenStream, unformatted: bool) -> Result<()> {
+    let code = render(&generation.folder().join(file), tokens, unformatted)?;
+    generation.add_file(file, code);
     Ok(())
 }
 
 fn write_file<P: AsRef<Path>>(file: P, tokens: &TokenStream) -> Result<()> {
     let file = file.as_ref();
     // println!("writing file {}", &file.display());
-    let code = tokens.to_string();
+    let code = render(file, tokens, false)?;
     let mut buffer = File::create(&file).context(CreateFileError { file: file.clone() })?;
     buffer.write_all(&code.as_bytes()).context(WriteFileError { file })?;
     Ok(())
 }
 
+/// Renders the code for a file, pretty-printed unless `unformatted`.
+fn render(file: &Path, tokens: &TokenStream, unformatted: bool) -> Result<String> {
+    if unformatted {
+        return Ok(tokens.to_string());
+    }
+    let syntax = syn::parse2::<syn::File>(tokens.clone()).context(ParseGeneratedCodeError { file })?;
+    Ok(prettyplease::unparse(&syntax))
+}
+
 const SPEC_FOLDER: &str = "../azure-rest-api-specs/specification";
 
 // gets a sorted list of folders in ../azure-rest-api-specs/specification
@@ -1,8 +1,8 @@
-use crate::{codegen::create_generated_by_header, identifier::ident, write_file};
+use crate::{codegen::create_generated_by_header, identifier::ident, render, write_file};
 use proc_macro2::TokenStream;
 use quote::quote;
 use snafu::{ResultExt, Snafu};
-use std::path::Path;
+use std::{collections::HashMap, path::Path};
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -16,96 +16,68 @@ pub enum Error {
     WriteFileError {
         source: crate::Error,
     },
+    RenderError {
+        source: crate::Error,
+    },
 }
 
 pub fn create(feature_mod_names: &Vec<(String, String)>, path: &Path) -> Result<()> {
-    write_file(path, &create_body(feature_mod_names)?).context(WriteFileError)?;
+    write_file(path, &create_body(feature_mod_names, &HashMap::new())?).context(WriteFileError)?;
     Ok(())
 }
 
-fn create_body(feature_mod_names: &Vec<(String, String)>) -> Result<TokenStream> {
+/// Creates the code of `lib.rs`, without writing it.
+/// A module is included when its feature, or the feature of any of its operation groups, is enabled.
+pub fn create_code(feature_mod_names: &Vec<(String, String)>, group_features: &HashMap<String, Vec<String>>) -> Result<String> {
+    render(Path::new("lib.rs"), &create_body(feature_mod_names, group_features)?, false).context(RenderError)
+}
+
+fn create_body(feature_mod_names: &Vec<(String, String)>, group_features: &HashMap<String, Vec<String>>) -> Result<TokenStream> {
     let mut cfgs = TokenStream::new();
     for (feature_name, mod_name) in feature_mod_names {
         let mod_name = ident(mod_name).context(IdentModNameError {
             feature_name: feature_name.to_owned(),
             mod_name: mod_name.to_owned(),
         })?;
+        let cfg = match group_features.get(feature_name) {
+            Some(groups) if !groups.is_empty() => quote! { #[cfg(any(feature = #feature_name, #(feature = #groups),*))] },
+            _ => quote! { #[cfg(feature = #feature_name)] },
+        };
         cfgs.extend(quote! {
-            #[cfg(feature = #feature_name)]
+            #cfg
             mod #mod_name;
-            #[cfg(feature = #feature_name)]
-            pub use #mod_name::{models, operations, API_VERSION};
+            #cfg
+            pub use #mod_name::{config, models, operations, API_VERSION};
         });
     }
     let generated_by = create_generated_by_header();
     Ok(quote! {
         #generated_by
         #cfgs
-        use azure_core::setters;
-
-        pub fn config(
-            http_client: std::sync::Arc<std::boxed::Box<dyn azure_core::HttpClient>>,
-            token_credential: Box<dyn azure_core::TokenCredential>,
-        ) -> OperationConfigBuilder {
-            OperationConfigBuilder {
-                api_version: None,
-                http_client,
-                base_path: None,
-                token_credential,
-                token_credential_resource: None,
-            }
-        }
-
-        pub struct OperationConfigBuilder {
-            api_version: Option<String>,
-            http_client: std::sync::Arc<std::boxed::Box<dyn azure_core::HttpClient>>,
-            base_path: Option<String>,
-            token_credential: Box<dyn azure_core::TokenCredential>,
-            token_credential_resource: Option<String>,
-        }
-
-        impl OperationConfigBuilder {
-            setters! {
-                api_version: String => Some(api_version),
-                base_path: String => Some(base_path),
-                token_credential_resource: String => Some(token_credential_resource),
-            }
-
-            pub fn build(self) -> OperationConfig {
-                OperationConfig {
-                    api_version: self.api_version.unwrap_or(API_VERSION.to_owned()),
-                    http_client: self.http_client,
-                    base_path: self.base_path.unwrap_or("https://management.azure.com".to_owned()),
-                    token_credential: Some(self.token_credential),
-                    token_credential_resource: self.token_credential_resource.unwrap_or("https://management.azure.com/".to_owned()),
-                }
-            }
-        }
+        pub use autorust_runtime::{OperationConfig, OperationConfigBuilder};
+    })
+}
 
-        pub struct OperationConfig {
-            api_version: String,
-            http_client: std::sync::Arc<std::boxed::Box<dyn azure_core::HttpClient>>,
-            base_path: String,
-            token_credential: Option<Box<dyn azure_core::TokenCredential>>,
-            token_credential_resource: String,
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        impl OperationConfig {
-            pub fn api_version(&self) -> &str {
-                self.api_version.as_str()
-            }
-            pub fn http_client(&self) -> &dyn azure_core::HttpClient {
-                self.http_client.as_ref().as_ref()
-            }
-            pub fn base_path(&self) -> &str {
-                self.base_path.as_str()
-            }
-            pub fn token_credential(&self) -> Option<&dyn azure_core::TokenCredential> {
-                self.token_credential.as_deref()
-            }
-            pub fn token_credential_resource(&self) -> &str {
-                self.token_credential_resource.as_str()
-            }
-        }
-    })
+    #[test]
+    fn test_create_body_with_group_features() {
+        let feature_mod_names = vec![
+            ("package-2021".to_owned(), "package_2021".to_owned()),
+            ("package-2020".to_owned(), "package_2020".to_owned()),
+        ];
+        let mut group_features = HashMap::new();
+        group_features.insert(
+            "package-2021".to_owned(),
+            vec!["package-2021-widgets".to_owned(), "package-2021-gadgets".to_owned()],
+        );
+        let body = create_body(&feature_mod_names, &group_features).unwrap().to_string();
+        let cfg = quote! { #[cfg(any(feature = "package-2021", feature = "package-2021-widgets", feature = "package-2021-gadgets"))] };
+        assert!(body.contains(&format!("{} mod package_2021 ;", cfg)));
+        assert!(body.contains(&format!("{} pub use package_2021 ::", cfg)));
+        let cfg = quote! { #[cfg(feature = "package-2020")] };
+        assert!(body.contains(&format!("{} mod package_2020 ;", cfg)));
+    }
 }
@@ -0,0 +1,443 @@
+//! Incremental generation, where only the files with changed content are written.
+//! A manifest in the output folder records the inputs and the files of the last generation,
+//! so that the files which are no longer generated can be removed, without touching any other files.
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// The name of the manifest file in the output folder.
+pub const MANIFEST_FILE: &str = ".autorust.json";
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Could not read file {}: {}", file.display(), source))]
+    ReadFileError {
+        file: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("Could not create directory {}: {}", directory.display(), source))]
+    CreateDirectoryError {
+        directory: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("Could not write file {}: {}", file.display(), source))]
+    WriteFileError {
+        file: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("Could not parse manifest {}: {}", file.display(), source))]
+    ParseManifestError {
+        file: PathBuf,
+        source: serde_json::Error,
+    },
+    #[snafu(display("The manifest path {} is not relative to the output folder", file))]
+    ManifestPathError {
+        file: String,
+    },
+    #[snafu(display("Could not remove file {}: {}", file.display(), source))]
+    RemoveFileError {
+        file: PathBuf,
+        source: std::io::Error,
+    },
+    SerializeManifestError {
+        source: serde_json::Error,
+    },
+}
+
+/// What was generated into an output folder, as recorded in its manifest.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub generator_version: String,
+    /// The hashes of the specification files that were read, by their paths.
+    #[serde(default)]
+    pub inputs: BTreeMap<String, String>,
+    /// The hashes of the generated files, by their paths relative to the output folder.
+    pub files: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// Reads the manifest in an output folder. There is none before the first generation.
+    /// A manifest that can not be parsed is an error, as the files that it lists could not be removed.
+    pub fn read(folder: &Path) -> Result<Option<Self>> {
+        let file = folder.join(MANIFEST_FILE);
+        if !file.exists() {
+            return Ok(None);
+        }
+        let bytes = fs::read(&file).context(ReadFileError { file: &file })?;
+        let manifest = serde_json::from_slice(&bytes).context(ParseManifestError { file })?;
+        Ok(Some(manifest))
+    }
+}
+
+/// The files that have been generated for an output folder, before they are written.
+#[derive(Debug)]
+pub struct Generation {
+    folder: PathBuf,
+    inputs: Vec<PathBuf>,
+    /// The cargo features of the generated code, besides the feature of the whole module.
+    features: Vec<String>,
+    /// The optional dependencies that the generated code uses, by their name in `Cargo.toml`.
+    dependencies: Vec<String>,
+    /// The schemas that no models were created for, as they were not reachable from the operations.
+    pruned_models: Vec<String>,
+    /// The schemas whose models were renamed, as they differ from another schema with the same name.
+    renamed_models: Vec<String>,
+    /// The `x-ms-client-flatten` properties that were kept nested, as their fields would have the names of other fields.
+    unflattened_properties: Vec<String>,
+    /// The operations whose functions were renamed, as another operation in their operation group has a function with the same name.
+    renamed_functions: Vec<String>,
+    files: IndexMap<PathBuf, String>,
+}
+
+impl Generation {
+    pub fn new<P: Into<PathBuf>>(folder: P) -> Self {
+        Self {
+            folder: folder.into(),
+            inputs: Vec::new(),
+            features: Vec::new(),
+            dependencies: Vec::new(),
+            pruned_models: Vec::new(),
+            renamed_models: Vec::new(),
+            unflattened_properties: Vec::new(),
+            renamed_functions: Vec::new(),
+            files: IndexMap::new(),
+        }
+    }
+
+    pub fn folder(&self) -> &Path {
+        &self.folder
+    }
+
+    /// Adds a specification file that the code was generated from.
+    pub fn add_input<P: Into<PathBuf>>(&mut self, input: P) {
+        let input = input.into();
+        if !self.inputs.contains(&input) {
+            self.inputs.push(input);
+        }
+    }
+
+    pub fn features(&self) -> &[String] {
+        &self.features
+    }
+
+    pub fn add_feature(&mut self, feature: String) {
+        if !self.features.contains(&feature) {
+            self.features.push(feature);
+        }
+    }
+
+    pub fn dependencies(&self) -> &[String] {
+        &self.dependencies
+    }
+
+    pub fn add_dependency(&mut self, dependency: &str) {
+        if !self.dependencies.iter().any(|other| other == dependency) {
+            self.dependencies.push(dependency.to_owned());
+        }
+    }
+
+    pub fn pruned_models(&self) -> &[String] {
+        &self.pruned_models
+    }
+
+    pub fn add_pruned_model(&mut self, model: String) {
+        self.pruned_models.push(model);
+    }
+
+    pub fn renamed_models(&self) -> &[String] {
+        &self.renamed_models
+    }
+
+    pub fn add_renamed_model(&mut self, model: String) {
+        self.renamed_models.push(model);
+    }
+
+    pub fn unflattened_properties(&self) -> &[String] {
+        &self.unflattened_properties
+    }
+
+    pub fn add_unflattened_property(&mut self, property: String) {
+        self.unflattened_properties.push(property);
+    }
+
+    pub fn renamed_functions(&self) -> &[String] {
+        &self.renamed_functions
+    }
+
+    pub fn add_renamed_function(&mut self, function: String) {
+        self.renamed_functions.push(function);
+    }
+
+    /// Gets the content of a file, where the path is relative to the output folder.
+    pub fn file<P: AsRef<Path>>(&self, file: P) -> Option<&str> {
+        self.files.get(file.as_ref()).map(String::as_str)
+    }
+
+    /// Adds a file, where the path is relative to the output folder.
+    pub fn add_file<P: Into<PathBuf>>(&mut self, file: P, content: String) {
+        self.files.insert(file.into(), content);
+    }
+
+    /// Adds the inputs and files of another generation, where the files are put in a subfolder.
+    pub fn extend<P: AsRef<Path>>(&mut self, subfolder: P, generation: Generation) {
+        for input in generation.inputs {
+            self.add_input(input);
+        }
+        for feature in generation.features {
+            self.add_feature(feature);
+        }
+        for dependency in generation.dependencies {
+            self.add_dependency(&dependency);
+        }
+        self.pruned_models.extend(generation.pruned_models);
+        self.renamed_models.extend(generation.renamed_models);
+        self.unflattened_properties.extend(generation.unflattened_properties);
+        self.renamed_functions.extend(generation.renamed_functions);
+        for (file, content) in generation.files {
+            self.add_file(subfolder.as_ref().join(file), content);
+        }
+    }
+
+    /// Writes the files whose content differs from what is on disk, removes the files of the last generation
+    /// that are no longer generated, and updates the manifest.
+    pub fn write(&self) -> Result<Report> {
+        let folder = &self.folder;
+        fs::create_dir_all(folder).context(CreateDirectoryError { directory: folder })?;
+        let previous = Manifest::read(folder)?.unwrap_or_default();
+        // the manifest is only trusted to remove files within the output folder
+        if let Some(file) = previous.files.keys().find(|file| !is_manifest_path(file)) {
+            return ManifestPathError { file }.fail();
+        }
+        let mut manifest = Manifest {
+            generator_version: env!("CARGO_PKG_VERSION").to_owned(),
+            ..Default::default()
+        };
+        for input in &self.inputs {
+            let bytes = fs::read(input).context(ReadFileError { file: input })?;
+            manifest.inputs.insert(input.to_string_lossy().into_owned(), hash(&bytes));
+        }
+
+        let mut report = Report::default();
+        for (file, content) in &self.files {
+            manifest.files.insert(to_manifest_path(file), hash(content.as_bytes()));
+            let path = folder.join(file);
+            let existing = if path.exists() {
+                Some(fs::read(&path).context(ReadFileError { file: &path })?)
+            } else {
+                None
+            };
+            match existing {
+                Some(existing) if existing == content.as_bytes() => {
+                    report.unchanged += 1;
+                    continue;
+                }
+                Some(_) => report.changed.push(file.clone()),
+                None => report.added.push(file.clone()),
+            }
+            if let Some(directory) = path.parent() {
+                fs::create_dir_all(directory).context(CreateDirectoryError { directory })?;
+            }
+            fs::write(&path, content).context(WriteFileError { file: &path })?;
+        }
+
+        for file in previous.files.keys() {
+            if manifest.files.contains_key(file) {
+                continue;
+            }
+            let path = folder.join(file);
+            if path.exists() {
+                fs::remove_file(&path).context(RemoveFileError { file: &path })?;
+            }
+            remove_empty_directories(folder, &path);
+            report.removed.push(PathBuf::from(file));
+        }
+
+        if manifest != previous {
+            let json = serde_json::to_string_pretty(&manifest).context(SerializeManifestError)?;
+            let file = folder.join(MANIFEST_FILE);
+            fs::write(&file, json + "\n").context(WriteFileError { file: &file })?;
+        }
+        Ok(report)
+    }
+}
+
+/// The files that were written or removed by a generation.
+#[derive(Debug, Default, PartialEq)]
+pub struct Report {
+    pub added: Vec<PathBuf>,
+    pub changed: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub unchanged: usize,
+}
+
+impl Report {
+    pub fn is_unchanged(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} added, {} changed, {} removed, {} unchanged",
+            self.added.len(),
+            self.changed.len(),
+            self.removed.len(),
+            self.unchanged
+        )
+    }
+}
+
+/// The path of a file in the manifest, which uses `/` on every platform.
+fn to_manifest_path(file: &Path) -> String {
+    file.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whether a path from a manifest is relative and stays within the output folder.
+fn is_manifest_path(file: &str) -> bool {
+    !file.is_empty()
+        && Path::new(file)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Removes the directories of a removed file that are now empty, up to the output folder.
+fn remove_empty_directories(folder: &Path, file: &Path) {
+    let mut directory = file.parent();
+    while let Some(dir) = directory {
+        if dir == folder || !dir.starts_with(folder) || fs::remove_dir(dir).is_err() {
+            break;
+        }
+        directory = dir.parent();
+    }
+}
+
+/// A 64-bit FNV-1a hash, which is stable across platforms and Rust versions, unlike the `Hasher` in `std`.
+fn hash(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("autorust_manifest_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        folder
+    }
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash(b""), "cbf29ce484222325");
+        assert_eq!(hash(b"a"), "af63dc4c8601ec8c");
+    }
+
+    #[test]
+    fn test_to_manifest_path() {
+        assert_eq!(to_manifest_path(&Path::new("src").join("v1").join("models.rs")), "src/v1/models.rs");
+    }
+
+    #[test]
+    fn test_is_manifest_path() {
+        assert!(is_manifest_path("src/v1/models.rs"));
+        assert!(!is_manifest_path(""));
+        assert!(!is_manifest_path("/etc/passwd"));
+        assert!(!is_manifest_path("../lib.rs"));
+        assert!(!is_manifest_path("src/../../lib.rs"));
+        assert!(!is_manifest_path("./lib.rs"));
+    }
+
+    #[test]
+    fn test_write_rejects_paths_outside_folder() {
+        let folder = temp_folder("outside");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join(MANIFEST_FILE), r#"{"generator_version":"","files":{"../a.rs":""}}"#).unwrap();
+        let generation = Generation::new(&folder);
+        assert!(matches!(generation.write(), Err(Error::ManifestPathError { .. })));
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_read_rejects_corrupt_manifest() {
+        let folder = temp_folder("corrupt");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join(MANIFEST_FILE), r#"{"generator_version":"#).unwrap();
+        assert!(matches!(Manifest::read(&folder), Err(Error::ParseManifestError { .. })));
+        let generation = Generation::new(&folder);
+        assert!(matches!(generation.write(), Err(Error::ParseManifestError { .. })));
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_write_only_changes() -> Result<()> {
+        let folder = temp_folder("changes");
+        let input = temp_folder("changes_input");
+        fs::write(&input, "a").unwrap();
+        let mut generation = Generation::new(&folder);
+        generation.add_input(&input);
+        generation.add_file("a.rs", "a".to_owned());
+        generation.add_file(Path::new("v1").join("b.rs"), "b".to_owned());
+        let report = generation.write()?;
+        assert_eq!(report.added.len(), 2);
+
+        let report = generation.write()?;
+        assert!(report.is_unchanged());
+        assert_eq!(report.unchanged, 2);
+
+        fs::write(folder.join("other.rs"), "not generated").unwrap();
+        let mut generation = Generation::new(&folder);
+        generation.add_file("a.rs", "a2".to_owned());
+        let report = generation.write()?;
+        assert_eq!(report.changed, vec![PathBuf::from("a.rs")]);
+        assert_eq!(report.removed, vec![PathBuf::from("v1/b.rs")]);
+        assert!(!folder.join("v1").exists());
+        assert!(folder.join("other.rs").exists());
+
+        let manifest = Manifest::read(&folder)?.unwrap();
+        assert_eq!(manifest.files.keys().collect::<Vec<_>>(), ["a.rs"]);
+        // the inputs of the last generation are recorded along with their hashes
+        assert!(manifest.inputs.is_empty());
+
+        // a changed input only changes the manifest
+        let mut generation = Generation::new(&folder);
+        generation.add_input(&input);
+        generation.add_file("a.rs", "a2".to_owned());
+        generation.write()?;
+        let manifest = Manifest::read(&folder)?.unwrap();
+        let inputs: Vec<_> = manifest
+            .inputs
+            .iter()
+            .map(|(file, hash)| (PathBuf::from(file), hash.as_str()))
+            .collect();
+        assert_eq!(inputs, [(input.clone(), "af63dc4c8601ec8c")]);
+        fs::write(&input, "b").unwrap();
+        let report = generation.write()?;
+        assert!(report.is_unchanged());
+        let manifest = Manifest::read(&folder)?.unwrap();
+        assert_eq!(manifest.inputs[&input.to_string_lossy().into_owned()], hash(b"b"));
+        fs::remove_dir_all(&folder).unwrap();
+        fs::remove_file(&input).unwrap();
+        Ok(())
+    }
+}
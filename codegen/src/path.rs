@@ -1,12 +1,21 @@
 use path_abs::PathMut;
 use snafu::{ResultExt, Snafu};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 #[derive(Debug, Snafu)]
 pub enum Error {
-    PopUpPath { source: path_abs::Error },
-    AppendPath { source: path_abs::Error },
+    PopUpPath {
+        source: path_abs::Error,
+    },
+    AppendPath {
+        source: path_abs::Error,
+    },
+    #[snafu(display("There is no relative path from {} to {}", from.display(), to.display()))]
+    RelativePath {
+        from: PathBuf,
+        to: PathBuf,
+    },
 }
 
 /// Joins two files paths together
@@ -22,6 +31,31 @@ pub fn join<P1: AsRef<Path>, P2: AsRef<Path>>(a: P1, b: P2) -> Result<PathBuf> {
     Ok(c)
 }
 
+/// Gets the path of a file relative to another file, such that joining them gives the file
+///
+/// Both paths are expected to be normalized, as returned by `join`.
+pub fn relative<P1: AsRef<Path>, P2: AsRef<Path>>(from: P1, to: P2) -> Result<PathBuf> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    let folder = if from.extension().is_some() {
+        from.parent().unwrap_or(from)
+    } else {
+        from
+    };
+    let folder: Vec<_> = folder.components().filter(|c| c != &Component::CurDir).collect();
+    let file: Vec<_> = to.components().filter(|c| c != &Component::CurDir).collect();
+    let common = folder.iter().zip(&file).take_while(|(a, b)| a == b).count();
+    // the name of a parent folder is not known
+    if folder[common..].iter().any(|c| !matches!(c, Component::Normal(_))) {
+        return RelativePath { from, to }.fail();
+    }
+    let mut c = PathBuf::new();
+    for _ in common..folder.len() {
+        c.push("..");
+    }
+    c.extend(&file[common..]);
+    Ok(c)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,4 +71,16 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_path_relative() -> Result<()> {
+        let a = "../azure-rest-api-specs/specification/vmware/resource-manager/Microsoft.AVS/stable/2020-03-20/vmware.json";
+        let b = "../azure-rest-api-specs/specification/common-types/resource-management/v1/types.json";
+        let c = relative(a, b)?;
+        assert_eq!(c, PathBuf::from("../../../../../common-types/resource-management/v1/types.json"));
+        assert_eq!(join(a, c)?, PathBuf::from(b));
+        assert_eq!(relative("specs/a.json", "specs/b.json")?, PathBuf::from("b.json"));
+        assert!(relative("../specs/a.json", "b.json").is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,350 @@
+use crate::identifier::{ident, UniqueIdents};
+use heck::{CamelCase, SnakeCase};
+use proc_macro2::TokenStream;
+use quote::quote;
+use snafu::{ResultExt, Snafu};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+#[derive(Debug, Snafu)]
+pub enum Error {
+    IdentError { source: crate::identifier::Error },
+}
+
+/// A segment of a path in the spec.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Segment {
+    Literal(String),
+    Parameter(String),
+}
+
+/// The path of an ARM resource, such as
+/// `/subscriptions/{subscriptionId}/resourceGroups/{resourceGroupName}/providers/Microsoft.AVS/privateClouds/{privateCloudName}`.
+/// Each parameter is a whole segment that follows a literal one, such as the type of the resource.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceIdPattern {
+    template: String,
+    segments: Vec<Segment>,
+}
+
+impl ResourceIdPattern {
+    /// Parses the path of an operation, if it is the path of an ARM resource.
+    pub fn parse(path: &str) -> Option<Self> {
+        let mut segments = Vec::new();
+        for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+            if segment.starts_with('{') && segment.ends_with('}') {
+                let name = &segment[1..segment.len() - 1];
+                if name.is_empty() || name.contains(|ch| ch == '{' || ch == '}') {
+                    return None;
+                }
+                // a parameter may not span segments, so it must follow a literal
+                match segments.last() {
+                    Some(Segment::Literal(_)) => segments.push(Segment::Parameter(name.to_owned())),
+                    _ => return None,
+                }
+            } else if segment.contains(|ch| ch == '{' || ch == '}') {
+                return None;
+            } else {
+                segments.push(Segment::Literal(segment.to_owned()));
+            }
+        }
+        let is_arm = match segments.first() {
+            Some(Segment::Literal(first)) => {
+                first.eq_ignore_ascii_case("subscriptions") || segments.iter().any(|segment| is_literal(segment, "providers"))
+            }
+            _ => false,
+        };
+        match segments.last() {
+            Some(Segment::Parameter(_)) if is_arm => Some(Self {
+                template: path.to_owned(),
+                segments,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// The template with the literals in lower case, as ARM paths are case-insensitive.
+    pub fn key(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(literal) => literal.to_lowercase(),
+                Segment::Parameter(name) => format!("{{{}}}", name),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// The names of the parameters, in the order of the path.
+    pub fn parameters(&self) -> Vec<&str> {
+        self.segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Parameter(name) => Some(name.as_str()),
+                Segment::Literal(_) => None,
+            })
+            .collect()
+    }
+
+    /// The type of the resource, such as `Microsoft.AVS/privateClouds`, which is the namespace after the last `providers`,
+    /// followed by the literal segments before each parameter.
+    pub fn resource_type(&self) -> Option<String> {
+        let providers = self.segments.iter().rposition(|segment| is_literal(segment, "providers"))?;
+        let mut parts = Vec::new();
+        match self.segments.get(providers + 1) {
+            Some(Segment::Literal(namespace)) => parts.push(namespace.as_str()),
+            _ => return None,
+        }
+        for pair in self.segments[providers + 2..].windows(2) {
+            if let [Segment::Literal(resource_type), Segment::Parameter(_)] = pair {
+                parts.push(resource_type);
+            }
+        }
+        Some(parts.join("/"))
+    }
+
+    /// Whether the path of an operation is the path of this resource, or of something below it.
+    pub fn is_prefix_of(&self, path: &str) -> bool {
+        let path: Vec<_> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+        path.len() >= self.segments.len()
+            && self.segments.iter().zip(path).all(|(segment, path_segment)| match segment {
+                Segment::Literal(literal) => literal.eq_ignore_ascii_case(path_segment),
+                Segment::Parameter(name) => path_segment == format!("{{{}}}", name),
+            })
+    }
+
+    /// The name for the type, from the last parameter, such as `PrivateCloudId` for `{privateCloudName}`.
+    pub fn type_name(&self) -> String {
+        let name = self.parameters().last().copied().unwrap_or_default();
+        let name = name.strip_suffix("Name").or_else(|| name.strip_suffix("Id")).unwrap_or(name);
+        format!("{}Id", name.to_camel_case())
+    }
+}
+
+fn is_literal(segment: &Segment, text: &str) -> bool {
+    match segment {
+        Segment::Literal(literal) => literal.eq_ignore_ascii_case(text),
+        Segment::Parameter(_) => false,
+    }
+}
+
+/// Gets the names of the fields of a typed resource ID, one for each parameter.
+pub fn get_field_names(pattern: &ResourceIdPattern) -> Result<Vec<TokenStream>> {
+    let mut names = UniqueIdents::default();
+    pattern
+        .parameters()
+        .iter()
+        .map(|parameter| {
+            names
+                .insert(ident(&parameter.to_snake_case()).context(IdentError)?)
+                .context(IdentError)
+        })
+        .collect()
+}
+
+/// Creates the typed resource ID for a resource path, which parses and formats the ID.
+pub fn create_resource_id(type_name: &TokenStream, pattern: &ResourceIdPattern) -> Result<TokenStream> {
+    let template = pattern.template();
+    let doc = format!(" The ID of a resource with the path `{}`.", template);
+    let fields = get_field_names(pattern)?;
+    let mut fmt = String::new();
+    let mut bindings = Vec::new();
+    let mut checks = Vec::new();
+    let mut field_names = fields.iter();
+    for (i, segment) in pattern.segments.iter().enumerate() {
+        match segment {
+            Segment::Literal(literal) => {
+                fmt.push('/');
+                fmt.push_str(literal);
+                let binding = ident(&format!("segment_{}", i)).context(IdentError)?;
+                checks.push(quote! { #binding.eq_ignore_ascii_case(#literal) });
+                bindings.push(binding);
+            }
+            Segment::Parameter(_) => {
+                fmt.push_str("/{}");
+                let field_name = field_names.next().expect("a field for each parameter");
+                checks.push(quote! { !#field_name.is_empty() });
+                bindings.push(field_name.clone());
+            }
+        }
+    }
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(try_from = "String", into = "String")]
+        pub struct #type_name {
+            #(pub #fields: String,)*
+        }
+        impl #type_name {
+            pub const TEMPLATE: &'static str = #template;
+            pub fn new(#(#fields: impl Into<String>),*) -> Self {
+                Self {
+                    #(#fields: #fields.into()),*
+                }
+            }
+        }
+        impl std::fmt::Display for #type_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, #fmt, #(self.#fields),*)
+            }
+        }
+        impl std::str::FromStr for #type_name {
+            type Err = ParseResourceIdError;
+            fn from_str(id: &str) -> std::result::Result<Self, Self::Err> {
+                let segments: Vec<&str> = id.trim_matches('/').split('/').collect();
+                match segments.as_slice() {
+                    [#(#bindings),*] if #(#checks)&&* => Ok(Self {
+                        #(#fields: #fields.to_string()),*
+                    }),
+                    _ => Err(ParseResourceIdError {
+                        id: id.to_owned(),
+                        template: Self::TEMPLATE,
+                    }),
+                }
+            }
+        }
+        impl std::convert::TryFrom<String> for #type_name {
+            type Error = ParseResourceIdError;
+            fn try_from(id: String) -> std::result::Result<Self, Self::Error> {
+                id.parse()
+            }
+        }
+        impl From<#type_name> for String {
+            fn from(id: #type_name) -> String {
+                id.to_string()
+            }
+        }
+        impl From<#type_name> for ResourceId {
+            fn from(id: #type_name) -> ResourceId {
+                ResourceId::new(id.to_string())
+            }
+        }
+    })
+}
+
+/// Creates the `resource_ids` module with the typed resource IDs, along with an untyped `ResourceId` for IDs of any type.
+pub fn create_resource_ids_module(resource_ids: Vec<TokenStream>) -> TokenStream {
+    quote! {
+        pub mod resource_ids {
+            use serde::{Deserialize, Serialize};
+
+            /// The error when a resource ID does not match the path of its type.
+            #[derive(Clone, Debug, PartialEq)]
+            pub struct ParseResourceIdError {
+                pub id: String,
+                pub template: &'static str,
+            }
+            impl std::fmt::Display for ParseResourceIdError {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    write!(f, "resource ID {} does not match {}", self.id, self.template)
+                }
+            }
+            impl std::error::Error for ParseResourceIdError {}
+
+            /// The ID of a resource of any type, which can be parsed into a typed resource ID.
+            #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+            #[serde(transparent)]
+            pub struct ResourceId(String);
+            impl ResourceId {
+                pub fn new(id: impl Into<String>) -> Self {
+                    Self(id.into())
+                }
+                pub fn as_str(&self) -> &str {
+                    &self.0
+                }
+                /// Parses the ID into a typed resource ID.
+                pub fn parse<T: std::str::FromStr>(&self) -> std::result::Result<T, T::Err> {
+                    self.0.parse()
+                }
+                pub fn subscription_id(&self) -> Option<&str> {
+                    self.segment_after("subscriptions")
+                }
+                pub fn resource_group_name(&self) -> Option<&str> {
+                    self.segment_after("resourceGroups")
+                }
+                /// The name of the resource, which is the last segment.
+                pub fn name(&self) -> Option<&str> {
+                    self.0.trim_end_matches('/').rsplit('/').next().filter(|name| !name.is_empty())
+                }
+                fn segment_after(&self, literal: &str) -> Option<&str> {
+                    let mut segments = self.0.split('/');
+                    segments.find(|segment| segment.eq_ignore_ascii_case(literal))?;
+                    segments.next().filter(|segment| !segment.is_empty())
+                }
+            }
+            impl std::fmt::Display for ResourceId {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.write_str(&self.0)
+                }
+            }
+            impl From<String> for ResourceId {
+                fn from(id: String) -> Self {
+                    Self(id)
+                }
+            }
+            impl AsRef<str> for ResourceId {
+                fn as_ref(&self) -> &str {
+                    &self.0
+                }
+            }
+
+            #(#resource_ids)*
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIVATE_CLOUD: &str =
+        "/subscriptions/{subscriptionId}/resourceGroups/{resourceGroupName}/providers/Microsoft.AVS/privateClouds/{privateCloudName}";
+
+    #[test]
+    fn test_parse_private_cloud() {
+        let pattern = ResourceIdPattern::parse(PRIVATE_CLOUD).unwrap();
+        assert_eq!(pattern.parameters(), ["subscriptionId", "resourceGroupName", "privateCloudName"]);
+        assert_eq!(pattern.resource_type().as_deref(), Some("Microsoft.AVS/privateClouds"));
+        assert_eq!(pattern.type_name(), "PrivateCloudId");
+    }
+
+    #[test]
+    fn test_parse_not_a_resource() {
+        assert_eq!(ResourceIdPattern::parse("/providers/Microsoft.AVS/operations"), None);
+        assert_eq!(
+            ResourceIdPattern::parse("/{scope}/providers/Microsoft.Authorization/locks/{lockName}"),
+            None
+        );
+        assert_eq!(
+            ResourceIdPattern::parse("/subscriptions/{subscriptionId}/providers/Microsoft.AVS/checkName"),
+            None
+        );
+        assert_eq!(ResourceIdPattern::parse("/pets/{petId}"), None);
+    }
+
+    #[test]
+    fn test_nested_resource_type() {
+        let pattern = ResourceIdPattern::parse(&format!("{}/clusters/{{clusterName}}", PRIVATE_CLOUD)).unwrap();
+        assert_eq!(pattern.resource_type().as_deref(), Some("Microsoft.AVS/privateClouds/clusters"));
+        assert_eq!(pattern.type_name(), "ClusterId");
+    }
+
+    #[test]
+    fn test_subscription() {
+        let pattern = ResourceIdPattern::parse("/subscriptions/{subscriptionId}").unwrap();
+        assert_eq!(pattern.resource_type(), None);
+        assert_eq!(pattern.type_name(), "SubscriptionId");
+    }
+
+    #[test]
+    fn test_is_prefix_of() {
+        let pattern = ResourceIdPattern::parse(PRIVATE_CLOUD).unwrap();
+        assert!(pattern.is_prefix_of(PRIVATE_CLOUD));
+        assert!(pattern.is_prefix_of(&format!("{}/clusters", PRIVATE_CLOUD.replace("privateClouds", "PrivateClouds"))));
+        assert!(!pattern.is_prefix_of("/subscriptions/{subscriptionId}/resourceGroups/{resourceGroupName}"));
+        assert!(!pattern.is_prefix_of(&PRIVATE_CLOUD.replace("{privateCloudName}", "{name}")));
+    }
+}
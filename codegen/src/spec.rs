@@ -16,6 +16,9 @@ pub struct Spec {
     docs: IndexMap<PathBuf, OpenAPI>,
     schemas: IndexMap<RefKey, Schema>,
     parameters: IndexMap<RefKey, Parameter>,
+    deprecations: IndexMap<PointerKey, Deprecation>,
+    reference_client_names: IndexMap<PointerKey, String>,
+    base_paths: IndexMap<PathBuf, String>,
     input_files_paths: IndexSet<PathBuf>,
 }
 
@@ -25,8 +28,17 @@ impl Spec {
     /// This eagerly collects all the schemas and parametes for the docs
     pub fn read_files<P: AsRef<Path>>(input_files_paths: &[P]) -> Result<Self> {
         let mut docs: IndexMap<PathBuf, OpenAPI> = IndexMap::new();
+        let mut deprecations = IndexMap::new();
+        let mut reference_client_names = IndexMap::new();
+        let mut base_paths = IndexMap::new();
         for file_path in input_files_paths {
-            Spec::read_file(&mut docs, file_path)?;
+            Spec::read_file(
+                &mut docs,
+                &mut deprecations,
+                &mut reference_client_names,
+                &mut base_paths,
+                file_path,
+            )?;
         }
 
         let mut schemas: IndexMap<RefKey, Schema> = IndexMap::new();
@@ -57,20 +69,51 @@ impl Spec {
             docs,
             schemas,
             parameters,
+            deprecations,
+            reference_client_names,
+            base_paths,
             input_files_paths: input_files_paths.iter().map(|f| f.as_ref().to_owned()).collect(),
         })
     }
 
     /// Read a file and references too, recursively into the map
-    fn read_file<P: AsRef<Path>>(docs: &mut IndexMap<PathBuf, OpenAPI>, file_path: P) -> Result<()> {
+    fn read_file<P: AsRef<Path>>(
+        docs: &mut IndexMap<PathBuf, OpenAPI>,
+        deprecations: &mut IndexMap<PointerKey, Deprecation>,
+        reference_client_names: &mut IndexMap<PointerKey, String>,
+        base_paths: &mut IndexMap<PathBuf, String>,
+        file_path: P,
+    ) -> Result<()> {
         let file_path = file_path.as_ref();
         if !docs.contains_key(file_path) {
-            let doc = openapi::parse(&file_path)?;
+            let value = openapi::parse_value(&file_path)?;
+            for (pointer, deprecation) in openapi::get_deprecations(&value) {
+                deprecations.insert(
+                    PointerKey {
+                        file_path: PathBuf::from(file_path),
+                        pointer,
+                    },
+                    deprecation,
+                );
+            }
+            for (pointer, client_name) in openapi::get_reference_client_names(&value) {
+                reference_client_names.insert(
+                    PointerKey {
+                        file_path: PathBuf::from(file_path),
+                        pointer,
+                    },
+                    client_name,
+                );
+            }
+            if let Some(base_path) = openapi::get_base_path(&value) {
+                base_paths.insert(PathBuf::from(file_path), base_path);
+            }
+            let doc = openapi::from_value(&file_path, value)?;
             let ref_files = openapi::get_reference_file_paths(&doc);
             docs.insert(PathBuf::from(file_path), doc);
             for ref_file in ref_files {
                 let child_path = path::join(&file_path, &ref_file).context(PathJoin)?;
-                Spec::read_file(docs, &child_path)?;
+                Spec::read_file(docs, deprecations, reference_client_names, base_paths, &child_path)?;
             }
         }
         Ok(())
@@ -84,23 +127,72 @@ impl Spec {
         self.docs.iter().filter(move |(p, _)| self.is_input_file(p))
     }
 
+    /// The base path of the operations from the `schemes`, `host` and `basePath` of the first input file that has a `host`
+    pub fn base_path(&self) -> Option<&str> {
+        self.input_files_paths
+            .iter()
+            .find_map(|path| self.base_paths.get(path))
+            .map(String::as_str)
+    }
+
     pub fn is_input_file<P: AsRef<Path>>(&self, path: P) -> bool {
         self.input_files_paths.contains(path.as_ref())
     }
 
-    /// Find the schema for a given doc path and reference
-    pub fn resolve_schema_ref<P: AsRef<Path>>(&self, doc_path: P, reference: Reference) -> Result<ResolvedSchema> {
+    /// Find the key of the schema for a given doc path and reference
+    pub fn schema_ref_key<P: AsRef<Path>>(&self, doc_path: P, reference: &Reference) -> Result<RefKey> {
         let doc_path = doc_path.as_ref();
-        let full_path = match reference.file {
+        let full_path = match &reference.file {
             None => doc_path.to_owned(),
-            Some(file) => path::join(doc_path, &file).context(PathJoin)?,
+            Some(file) => path::join(doc_path, file).context(PathJoin)?,
         };
-
-        let name = reference.name.ok_or_else(|| Error::NoNameInReference)?;
-        let ref_key = RefKey {
+        let name = reference.name.clone().ok_or_else(|| Error::NoNameInReference)?;
+        Ok(RefKey {
             file_path: full_path,
             name,
-        };
+        })
+    }
+
+    /// Whether a definition is marked as deprecated with `x-ms-deprecated` or `deprecated: true`
+    pub fn schema_deprecation(&self, ref_key: &RefKey) -> Option<&Deprecation> {
+        self.deprecation(&ref_key.file_path, &["definitions", &ref_key.name])
+    }
+
+    /// Whether a property of a definition is marked as deprecated with `x-ms-deprecated` or `deprecated: true`
+    pub fn property_deprecation(&self, ref_key: &RefKey, property_name: &str) -> Option<&Deprecation> {
+        self.deprecation(&ref_key.file_path, &["definitions", &ref_key.name, "properties", property_name])
+    }
+
+    /// Whether an operation is marked as deprecated with `x-ms-deprecated` or `deprecated: true`
+    pub fn operation_deprecation(&self, doc_file: &Path, path: &str, verb: &OperationVerb) -> Option<&Deprecation> {
+        self.deprecation(doc_file, &["paths", path, verb.verb_name()])
+    }
+
+    /// The `x-ms-client-name` of a property of a definition that is a `$ref`, which the references of `autorust_openapi` do not keep
+    pub fn reference_client_name(&self, ref_key: &RefKey, property_name: &str) -> Option<&str> {
+        self.reference_client_names
+            .get(&PointerKey {
+                file_path: ref_key.file_path.clone(),
+                pointer: openapi::create_pointer(&["definitions", &ref_key.name, "properties", property_name]),
+            })
+            .map(String::as_str)
+    }
+
+    fn deprecation(&self, file_path: &Path, tokens: &[&str]) -> Option<&Deprecation> {
+        self.deprecations.get(&PointerKey {
+            file_path: file_path.to_owned(),
+            pointer: openapi::create_pointer(tokens),
+        })
+    }
+
+    /// Find the schema for a given key
+    pub fn schema(&self, ref_key: &RefKey) -> Option<&Schema> {
+        self.schemas.get(ref_key)
+    }
+
+    /// Find the schema for a given doc path and reference
+    pub fn resolve_schema_ref<P: AsRef<Path>>(&self, doc_path: P, reference: Reference) -> Result<ResolvedSchema> {
+        let ref_key = self.schema_ref_key(doc_path, &reference)?;
         let schema = self
             .schemas
             .get(&ref_key)
@@ -113,6 +205,8 @@ impl Spec {
     }
 
     /// Find the parameter for a given doc path and reference
+    ///
+    /// The schema references of a parameter from another file are changed to be relative to the doc path.
     pub fn resolve_parameter_ref<P: AsRef<Path>>(&self, doc_path: P, reference: Reference) -> Result<Parameter> {
         let doc_path = doc_path.as_ref();
         let full_path = match reference.file {
@@ -124,7 +218,33 @@ impl Spec {
             file_path: full_path,
             name,
         };
-        Ok(self.parameters.get(&ref_key).context(ParameterNotFound { ref_key })?.clone())
+        let mut parameter = self
+            .parameters
+            .get(&ref_key)
+            .context(ParameterNotFound { ref_key: ref_key.clone() })?
+            .clone();
+        if ref_key.file_path != doc_path {
+            let mut rebase = |reference: &mut Reference| -> Result<()> {
+                let file_path = match &reference.file {
+                    None => ref_key.file_path.clone(),
+                    Some(file) => path::join(&ref_key.file_path, file).context(PathJoin)?,
+                };
+                reference.file = if file_path == doc_path {
+                    None
+                } else {
+                    let file = path::relative(doc_path, &file_path).context(PathJoin)?;
+                    Some(file.to_string_lossy().replace('\\', "/"))
+                };
+                Ok(())
+            };
+            for schema in parameter.schema.iter_mut().chain(parameter.common.items.as_mut()) {
+                match schema {
+                    ReferenceOr::Reference { reference, .. } => rebase(reference)?,
+                    ReferenceOr::Item(schema) => visit_schema_references_mut(schema, &mut rebase)?,
+                }
+            }
+        }
+        Ok(parameter)
     }
 
     /// Resolve a reference or schema to a resolved schema
@@ -231,6 +351,22 @@ pub struct RefKey {
     pub name: String,
 }
 
+/// The location of what is marked as deprecated or named, as a JSON pointer into a document
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct PointerKey {
+    file_path: PathBuf,
+    pointer: String,
+}
+
+/// An `x-ms-deprecated` or `deprecated: true` marker, which the types of `autorust_openapi` do not keep
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Deprecation {
+    /// The `description` of an `x-ms-deprecated` object
+    pub description: Option<String>,
+    /// The `replaced-by` of an `x-ms-deprecated` object, such as the operationId of the replacement operation
+    pub replaced_by: Option<String>,
+}
+
 pub struct ResolvedSchema {
     pub ref_key: Option<RefKey>,
     pub schema: Schema,
@@ -239,18 +375,127 @@ pub struct ResolvedSchema {
 /// Functionality related to Open API definitions
 pub mod openapi {
     use super::*;
+    use serde_json::Value;
 
     /// Parse an OpenAPI object from a file located at `path`
     pub fn parse<P: AsRef<Path>>(path: P) -> Result<OpenAPI> {
+        let value = parse_value(&path)?;
+        from_value(path, value)
+    }
+
+    /// Parse a file located at `path` as JSON, which keeps the extensions that an OpenAPI object does not
+    pub fn parse_value<P: AsRef<Path>>(path: P) -> Result<Value> {
         let path = path.as_ref();
         let bytes = fs::read(path).context(ReadFile { path: PathBuf::from(path) })?;
-        let api = if path.extension() == Some(OsStr::new("yaml")) || path.extension() == Some(OsStr::new("yml")) {
+        let value = if path.extension() == Some(OsStr::new("yaml")) || path.extension() == Some(OsStr::new("yml")) {
             serde_yaml::from_slice(&bytes).context(DeserializeYaml { path: PathBuf::from(path) })?
         } else {
             serde_json::from_slice(&bytes).context(DeserializeJson { path: PathBuf::from(path) })?
         };
 
-        Ok(api)
+        Ok(value)
+    }
+
+    /// Deserialize an OpenAPI object from the JSON of the file located at `path`
+    pub fn from_value<P: AsRef<Path>>(path: P, value: Value) -> Result<OpenAPI> {
+        serde_json::from_value(value).context(DeserializeJson {
+            path: PathBuf::from(path.as_ref()),
+        })
+    }
+
+    /// Returns the JSON pointers of the definitions, their properties and the operations that are marked as deprecated
+    pub fn get_deprecations(api: &Value) -> Vec<(String, Deprecation)> {
+        let mut deprecations = Vec::new();
+        for (name, definition) in objects(api.get("definitions")) {
+            if let Some(deprecation) = get_deprecation(definition) {
+                deprecations.push((create_pointer(&["definitions", name]), deprecation));
+            }
+            for (property_name, property) in objects(definition.get("properties")) {
+                if let Some(deprecation) = get_deprecation(property) {
+                    deprecations.push((create_pointer(&["definitions", name, "properties", property_name]), deprecation));
+                }
+            }
+        }
+        for (path, item) in objects(api.get("paths")) {
+            for (verb, operation) in objects(Some(item)) {
+                if let Some(deprecation) = get_deprecation(operation) {
+                    deprecations.push((create_pointer(&["paths", path, verb]), deprecation));
+                }
+            }
+        }
+        deprecations
+    }
+
+    /// Returns the JSON pointers and `x-ms-client-name` of the properties of the definitions that are a `$ref`
+    pub fn get_reference_client_names(api: &Value) -> Vec<(String, String)> {
+        let mut client_names = Vec::new();
+        for (name, definition) in objects(api.get("definitions")) {
+            for (property_name, property) in objects(definition.get("properties")) {
+                if property.get("$ref").is_none() {
+                    continue;
+                }
+                if let Some(client_name) = property.get("x-ms-client-name").and_then(Value::as_str) {
+                    client_names.push((
+                        create_pointer(&["definitions", name, "properties", property_name]),
+                        client_name.to_owned(),
+                    ));
+                }
+            }
+        }
+        client_names
+    }
+
+    /// Returns the base path of the operations from the `schemes`, `host` and `basePath` of an OpenAPI specification,
+    /// preferring `https` when there are several schemes, or `None` when there is no `host`
+    pub fn get_base_path(api: &Value) -> Option<String> {
+        let host = api.get("host")?.as_str()?;
+        let schemes: Vec<_> = api
+            .get("schemes")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect();
+        let scheme = match schemes.first() {
+            Some(scheme) if !schemes.contains(&"https") => scheme,
+            _ => "https",
+        };
+        let base_path = api
+            .get("basePath")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .trim_end_matches('/');
+        Some(format!("{}://{}{}", scheme, host, base_path))
+    }
+
+    /// The members of an object, if it is one
+    fn objects(value: Option<&Value>) -> impl Iterator<Item = (&String, &Value)> {
+        value.and_then(Value::as_object).into_iter().flatten()
+    }
+
+    /// Gets the `x-ms-deprecated` extension of an object, which is either `true` or an object with a `description` and a `replaced-by`,
+    /// or else its `deprecated: true`
+    fn get_deprecation(object: &Value) -> Option<Deprecation> {
+        match object.get("x-ms-deprecated") {
+            Some(Value::Bool(true)) => Some(Deprecation::default()),
+            Some(Value::Object(extension)) => {
+                let get = |name| extension.get(name).and_then(Value::as_str).map(str::to_owned);
+                Some(Deprecation {
+                    description: get("description"),
+                    replaced_by: get("replaced-by"),
+                })
+            }
+            _ if object.get("deprecated") == Some(&Value::Bool(true)) => Some(Deprecation::default()),
+            _ => None,
+        }
+    }
+
+    /// Creates a JSON pointer from its reference tokens, escaping the `~` and `/` in them
+    pub fn create_pointer(tokens: &[&str]) -> String {
+        tokens
+            .iter()
+            .map(|token| format!("/{}", token.replace('~', "~0").replace('/', "~1")))
+            .collect()
     }
 
     /// Returns a set of referenced relative file paths from an OpenAPI specficiation
@@ -331,6 +576,17 @@ pub mod openapi {
             })
             .collect()
     }
+
+    /// Get all references to parameters for an Open API specification
+    pub fn get_api_parameter_references(api: &OpenAPI) -> Vec<Reference> {
+        get_references(api)
+            .into_iter()
+            .filter_map(|rf| match rf {
+                TypedReference::Parameter(rp) => Some(rp),
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 pub enum OperationVerb<'a> {
@@ -443,6 +699,40 @@ pub fn get_schema_schema_references(schema: &Schema) -> Vec<Reference> {
         .collect()
 }
 
+/// Get all schema references for a given parameter
+pub fn get_parameter_schema_references(parameter: &Parameter) -> Vec<Reference> {
+    let mut refs = Vec::new();
+    for schema in parameter.schema.iter().chain(parameter.common.items.as_ref()) {
+        match schema {
+            ReferenceOr::Reference { reference, .. } => refs.push(reference.clone()),
+            ReferenceOr::Item(schema) => refs.extend(get_schema_schema_references(schema)),
+        }
+    }
+    refs
+}
+
+/// Calls a function with each of the schema references within a schema, which may change them
+fn visit_schema_references_mut(schema: &mut Schema, f: &mut dyn FnMut(&mut Reference) -> Result<()>) -> Result<()> {
+    for schema in schema
+        .properties
+        .values_mut()
+        .chain(schema.all_of.iter_mut())
+        .chain(schema.common.items.as_mut())
+    {
+        match schema {
+            ReferenceOr::Reference { reference, .. } => f(reference)?,
+            ReferenceOr::Item(schema) => visit_schema_references_mut(schema, f)?,
+        }
+    }
+    if let Some(AdditionalProperties::Schema(schema)) = &mut schema.additional_properties {
+        match schema {
+            ReferenceOr::Reference { reference, .. } => f(reference)?,
+            ReferenceOr::Item(schema) => visit_schema_references_mut(schema, f)?,
+        }
+    }
+    Ok(())
+}
+
 fn add_references_for_schema(list: &mut Vec<TypedReference>, schema: &Schema) {
     for (_, schema) in &schema.properties {
         match schema {
@@ -492,4 +782,77 @@ mod tests {
         );
         assert_eq!(function_name_from_operation_id("get"), (None, "get".to_owned()));
     }
+
+    #[test]
+    fn test_get_base_path() {
+        let api = serde_json::json!({ "host": "petstore.swagger.io", "basePath": "/v2", "schemes": ["http", "https"] });
+        assert_eq!(openapi::get_base_path(&api).as_deref(), Some("https://petstore.swagger.io/v2"));
+        let api = serde_json::json!({ "host": "localhost:8080", "basePath": "/", "schemes": ["http"] });
+        assert_eq!(openapi::get_base_path(&api).as_deref(), Some("http://localhost:8080"));
+        let api = serde_json::json!({ "host": "management.azure.com" });
+        assert_eq!(openapi::get_base_path(&api).as_deref(), Some("https://management.azure.com"));
+        assert_eq!(openapi::get_base_path(&serde_json::json!({ "basePath": "/v2" })), None);
+    }
+
+    #[test]
+    fn test_get_deprecations() {
+        let api = serde_json::json!({
+            "paths": {
+                "/widgets/{widgetName}": {
+                    "get": { "operationId": "Widgets_Get", "deprecated": true },
+                    "put": { "operationId": "Widgets_Put", "x-ms-deprecated": { "replaced-by": "Widgets_CreateOrUpdate" } },
+                    "delete": { "operationId": "Widgets_Delete", "x-ms-deprecated": false }
+                }
+            },
+            "definitions": {
+                "Widget": {
+                    "x-ms-deprecated": true,
+                    "properties": {
+                        "name": { "type": "string", "description": "Deprecated. Use title instead." },
+                        "size": { "type": "integer", "x-ms-deprecated": { "description": "Use dimensions instead." } }
+                    }
+                }
+            }
+        });
+        assert_eq!(
+            openapi::get_deprecations(&api),
+            vec![
+                ("/definitions/Widget".to_owned(), Deprecation::default()),
+                (
+                    "/definitions/Widget/properties/size".to_owned(),
+                    Deprecation {
+                        description: Some("Use dimensions instead.".to_owned()),
+                        replaced_by: None,
+                    }
+                ),
+                ("/paths/~1widgets~1{widgetName}/get".to_owned(), Deprecation::default()),
+                (
+                    "/paths/~1widgets~1{widgetName}/put".to_owned(),
+                    Deprecation {
+                        description: None,
+                        replaced_by: Some("Widgets_CreateOrUpdate".to_owned()),
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_reference_client_names() {
+        let api = serde_json::json!({
+            "definitions": {
+                "Widget": {
+                    "properties": {
+                        "owner": { "$ref": "#/definitions/User", "x-ms-client-name": "creator" },
+                        "maker": { "$ref": "#/definitions/User" },
+                        "title": { "type": "string", "x-ms-client-name": "name" }
+                    }
+                }
+            }
+        });
+        assert_eq!(
+            openapi::get_reference_client_names(&api),
+            vec![("/definitions/Widget/properties/owner".to_owned(), "creator".to_owned())]
+        );
+    }
 }
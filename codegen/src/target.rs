@@ -0,0 +1,198 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// The path of the local `azure_core` from a generated crate in the `services/mgmt` folder of `azure-sdk-for-rust`
+const AZURE_CORE_PATH: &str = "../../../sdk/core";
+
+/// The runtime that a generated crate targets, which provides the HTTP client and the token credential of its operations.
+/// The operations are the same for every target, only the `config` function and the dependencies differ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// The `HttpClient` and `TokenCredential` of `azure_core`, such as those from `azure_identity`.
+    AzureCore,
+    /// The `HttpClient` and `TokenCredential` traits of `autorust_runtime`, with a `reqwest::Client` as the HTTP client,
+    /// for crates that do not depend on `azure_core`.
+    Standalone,
+}
+
+impl Default for Target {
+    fn default() -> Self {
+        Target::AzureCore
+    }
+}
+
+impl Target {
+    pub const NAMES: &'static [&'static str] = &["azure_core", "standalone"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "azure_core" => Some(Target::AzureCore),
+            "standalone" => Some(Target::Standalone),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Target::AzureCore => "azure_core",
+            Target::Standalone => "standalone",
+        }
+    }
+
+    /// Whether the blocking operations can be created for the target. They need a synchronous HTTP client,
+    /// while the `HttpClient` and `TokenCredential` of `azure_core` need a tokio runtime.
+    pub fn supports_blocking(&self) -> bool {
+        match self {
+            Target::AzureCore => false,
+            Target::Standalone => true,
+        }
+    }
+
+    /// The `[dependencies]` of a generated crate that are specific to the target, as lines of `Cargo.toml`.
+    /// The `runtime_path` is the path of the `autorust_runtime` crate from the generated crate.
+    /// The HTTP clients of both targets run on tokio, so the runtime waits with the timer of tokio.
+    /// The blocking operations send their requests with the `ureq::Agent` of the `ureq` feature of the runtime.
+    pub fn dependencies(&self, runtime_path: &str, blocking: bool) -> String {
+        match self {
+            Target::AzureCore => format!(
+                r#"autorust_runtime = {{ path = "{}", features = ["azure_core", "tokio"] }}
+azure_core = {{ path = "{}", version = "0.1.0" }}
+reqwest = {{ version = "0.11", features = ["json"] }}
+"#,
+                runtime_path, AZURE_CORE_PATH
+            ),
+            Target::Standalone => format!(
+                r#"autorust_runtime = {{ path = "{}", features = ["reqwest", "tokio"{}] }}
+"#,
+                runtime_path,
+                if blocking { r#", "ureq""# } else { "" }
+            ),
+        }
+    }
+
+    /// The `[patch.crates-io]` of a generated crate, as lines of `Cargo.toml`.
+    /// The runtime depends on the `azure_core` of crates.io, which is patched with the local one of the generated crate,
+    /// so that its `HttpClient` and `TokenCredential` are those of the runtime.
+    pub fn patches(&self) -> String {
+        match self {
+            Target::AzureCore => format!(
+                r#"azure_core = {{ path = "{}" }}
+"#,
+                AZURE_CORE_PATH
+            ),
+            Target::Standalone => String::new(),
+        }
+    }
+
+    /// The `[dev-dependencies]` of a generated crate that are specific to the target, as lines of `Cargo.toml`.
+    pub fn dev_dependencies(&self) -> &'static str {
+        match self {
+            Target::AzureCore => {
+                r#"azure_identity = { path = "../../../sdk/identity", version = "0.1.0" }
+"#
+            }
+            Target::Standalone => "",
+        }
+    }
+
+    /// Creates the `config` function of a module with an `API_VERSION`,
+    /// which creates an `OperationConfigBuilder` from the HTTP client and the token credential of the target.
+    /// The `base_path` from the specification is the base path of a standalone crate, while it is the one of
+    /// Azure Resource Manager for `azure_core`.
+    /// With the blocking operations, a `blocking_config` function creates it with a `ureq::Agent` as the HTTP client.
+    pub fn create_config_function(&self, base_path: Option<&str>, blocking: bool) -> TokenStream {
+        let blocking_config = if blocking {
+            quote! {
+                /// The configuration of the blocking operations, with a `ureq::Agent` as the HTTP client.
+                pub fn blocking_config() -> autorust_runtime::OperationConfigBuilder {
+                    config(std::sync::Arc::new(autorust_runtime::ureq::Agent::new()))
+                }
+            }
+        } else {
+            TokenStream::new()
+        };
+        let config = match self {
+            Target::AzureCore => quote! {
+                pub fn config(
+                    http_client: std::sync::Arc<std::boxed::Box<dyn azure_core::HttpClient>>,
+                    token_credential: Box<dyn azure_core::TokenCredential>,
+                ) -> autorust_runtime::OperationConfigBuilder {
+                    autorust_runtime::OperationConfigBuilder::new(std::sync::Arc::new(autorust_runtime::azure::AzureHttpClient(http_client)))
+                        .api_version(API_VERSION)
+                        .base_path("https://management.azure.com")
+                        .token_credential(Box::new(autorust_runtime::azure::AzureTokenCredential(token_credential)))
+                        .token_credential_resource("https://management.azure.com/")
+                        .telemetry(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+                }
+            },
+            Target::Standalone => match base_path {
+                Some(base_path) => quote! {
+                    /// Another base path, and the token credential when the service needs one, may be set on the returned builder.
+                    /// The HTTP client may be an `autorust_runtime::reqwest::Client`.
+                    pub fn config(http_client: std::sync::Arc<dyn autorust_runtime::HttpClient>) -> autorust_runtime::OperationConfigBuilder {
+                        autorust_runtime::OperationConfigBuilder::new(http_client)
+                            .api_version(API_VERSION)
+                            .base_path(#base_path)
+                            .telemetry(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+                    }
+                },
+                None => quote! {
+                    /// The base path, and the token credential when the service needs one, are set on the returned builder.
+                    /// The HTTP client may be an `autorust_runtime::reqwest::Client`.
+                    pub fn config(http_client: std::sync::Arc<dyn autorust_runtime::HttpClient>) -> autorust_runtime::OperationConfigBuilder {
+                        autorust_runtime::OperationConfigBuilder::new(http_client)
+                            .api_version(API_VERSION)
+                            .telemetry(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+                    }
+                },
+            },
+        };
+        quote! {
+            #config
+            #blocking_config
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names() {
+        for name in Target::NAMES {
+            assert_eq!(Target::from_name(name).map(|target| target.name()), Some(*name));
+        }
+        assert_eq!(Target::from_name("reqwest"), None);
+    }
+
+    #[test]
+    fn test_standalone_does_not_use_azure_core() {
+        let target = Target::Standalone;
+        assert!(!target.dependencies("../runtime", false).contains("azure_core"));
+        assert!(target.patches().is_empty());
+        assert!(!target.dev_dependencies().contains("azure"));
+        assert!(!target.create_config_function(None, false).to_string().contains("azure"));
+    }
+
+    #[test]
+    fn test_standalone_base_path() {
+        let config = Target::Standalone
+            .create_config_function(Some("https://petstore.swagger.io/v2"), false)
+            .to_string();
+        assert!(config.contains(r#". base_path ("https://petstore.swagger.io/v2")"#));
+        assert!(!Target::Standalone
+            .create_config_function(None, false)
+            .to_string()
+            .contains("base_path ("));
+    }
+
+    #[test]
+    fn test_azure_core_patches_the_runtime() {
+        let target = Target::AzureCore;
+        assert!(target
+            .dependencies("../runtime", false)
+            .contains(r#"autorust_runtime = { path = "../runtime""#));
+        assert_eq!(target.patches(), "azure_core = { path = \"../../../sdk/core\" }\n");
+    }
+}
@@ -0,0 +1,24 @@
+//! The `azure_core` HTTP clients and token credentials, such as those from `azure_identity`.
+
+use crate::{BoxError, BoxFuture, Response};
+use bytes::Bytes;
+use std::sync::Arc;
+
+pub struct AzureHttpClient(pub Arc<Box<dyn azure_core::HttpClient>>);
+
+impl crate::HttpClient for AzureHttpClient {
+    fn execute_request(&self, request: http::Request<Bytes>) -> BoxFuture<'_, Result<Response, BoxError>> {
+        self.0.execute_request(request)
+    }
+}
+
+pub struct AzureTokenCredential(pub Box<dyn azure_core::TokenCredential>);
+
+impl crate::TokenCredential for AzureTokenCredential {
+    fn get_token<'a>(&'a self, resource: &'a str) -> BoxFuture<'a, Result<String, BoxError>> {
+        Box::pin(async move {
+            let token_response = self.0.get_token(resource).await?;
+            Ok(token_response.token.secret().to_owned())
+        })
+    }
+}
@@ -0,0 +1,120 @@
+//! Blocking on the operations, for programs without an async runtime, such as command line tools and build scripts.
+//! The HTTP client has to be synchronous, such as the `ureq::Agent` of the `ureq` feature,
+//! as there is no reactor to drive the futures of an async one.
+
+use crate::{OperationConfig, Pager, Request, Response, Result};
+use std::{
+    cell::Cell,
+    future::Future,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+    time::Instant,
+};
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+thread_local! {
+    /// The earliest deadline of the sleeps of the future that `block_on` is polling on this thread,
+    /// which is `None` outside of `block_on`.
+    static DEADLINE: Cell<Option<Option<Instant>>> = const { Cell::new(None) };
+}
+
+/// Has the `block_on` that is polling on this thread wake up at a deadline, which is its timer.
+/// It is false outside of `block_on`.
+pub(crate) fn wake_at(deadline: Instant) -> bool {
+    DEADLINE.with(|cell| match cell.get() {
+        Some(earliest) => {
+            cell.set(Some(Some(earliest.map_or(deadline, |earliest| earliest.min(deadline)))));
+            true
+        }
+        None => false,
+    })
+}
+
+/// Restores the deadline of an outer `block_on`, even when the future panics.
+struct RestoreDeadline(Option<Option<Instant>>);
+
+impl Drop for RestoreDeadline {
+    fn drop(&mut self) {
+        DEADLINE.with(|cell| cell.set(self.0));
+    }
+}
+
+/// Runs a future on the current thread until it is done, parking the thread while it waits, such as between retries.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    let _restore = RestoreDeadline(DEADLINE.with(|cell| cell.replace(Some(None))));
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        match DEADLINE.with(|cell| cell.replace(Some(None))).flatten() {
+            Some(deadline) => thread::park_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => thread::park(),
+        }
+    }
+}
+
+impl OperationConfig {
+    /// Sends a request through the pipeline, blocking until the response.
+    pub fn send_blocking(&self, request: Request) -> Result<Response> {
+        block_on(self.send(request))
+    }
+}
+
+/// The pages of a pageable operation, which are got as they are iterated, blocking for each one.
+pub struct BlockingPager<'a, T, E>(Pager<'a, T, E>);
+
+impl<'a, T, E> Pager<'a, T, E> {
+    /// Iterates over the pages, blocking for each one.
+    pub fn blocking(self) -> BlockingPager<'a, T, E> {
+        BlockingPager(self)
+    }
+}
+
+impl<'a, T, E> Iterator for BlockingPager<'a, T, E> {
+    type Item = std::result::Result<T, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        block_on(self.0.next_page())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sleep;
+    use std::time::Duration;
+
+    #[test]
+    fn test_block_on_sleep() {
+        assert_eq!(block_on(async { 1 }), 1);
+        let value = block_on(async {
+            sleep(Duration::from_millis(10)).await;
+            2
+        });
+        assert_eq!(value, 2);
+        assert!(!wake_at(Instant::now()));
+    }
+
+    #[test]
+    fn test_block_on_timer() {
+        let start = Instant::now();
+        block_on(async {
+            assert!(wake_at(Instant::now()));
+            for _ in 0..3 {
+                sleep(Duration::from_millis(5)).await;
+            }
+        });
+        assert!(start.elapsed() >= Duration::from_millis(15));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{
+    error::*,
+    policies::{BearerTokenPolicy, LoggingPolicy, RequestIdPolicy, TelemetryPolicy},
+    retry::{RetryOptions, RetryPolicy},
+    HttpClient, Pipeline, Policy, Request, Response, TokenCredential,
+};
+use std::{sync::Arc, time::Duration};
+
+/// How long to wait between the polls of a long-running operation, when the service does not say.
+const DEFAULT_POLLING_INTERVAL: Duration = Duration::from_secs(30);
+
+pub struct OperationConfigBuilder {
+    http_client: Arc<dyn HttpClient>,
+    api_version: Option<String>,
+    base_path: Option<String>,
+    token_credential: Option<Arc<dyn TokenCredential>>,
+    token_credential_resource: Option<String>,
+    polling_interval: Option<Duration>,
+    retry_options: RetryOptions,
+    application_id: Option<String>,
+    telemetry: Option<(String, String)>,
+    logged_headers: Vec<String>,
+    per_call_policies: Vec<Arc<dyn Policy>>,
+    per_retry_policies: Vec<Arc<dyn Policy>>,
+}
+
+impl OperationConfigBuilder {
+    pub fn new(http_client: Arc<dyn HttpClient>) -> Self {
+        Self {
+            http_client,
+            api_version: None,
+            base_path: None,
+            token_credential: None,
+            token_credential_resource: None,
+            polling_interval: None,
+            retry_options: RetryOptions::default(),
+            application_id: None,
+            telemetry: None,
+            logged_headers: Vec::new(),
+            per_call_policies: Vec::new(),
+            per_retry_policies: Vec::new(),
+        }
+    }
+
+    pub fn api_version<S: Into<String>>(mut self, api_version: S) -> Self {
+        self.api_version = Some(api_version.into());
+        self
+    }
+
+    pub fn base_path<S: Into<String>>(mut self, base_path: S) -> Self {
+        self.base_path = Some(base_path.into());
+        self
+    }
+
+    pub fn token_credential(mut self, token_credential: Box<dyn TokenCredential>) -> Self {
+        self.token_credential = Some(Arc::from(token_credential));
+        self
+    }
+
+    pub fn token_credential_resource<S: Into<String>>(mut self, token_credential_resource: S) -> Self {
+        self.token_credential_resource = Some(token_credential_resource.into());
+        self
+    }
+
+    pub fn polling_interval(mut self, polling_interval: Duration) -> Self {
+        self.polling_interval = Some(polling_interval);
+        self
+    }
+
+    pub fn retry_options(mut self, retry_options: RetryOptions) -> Self {
+        self.retry_options = retry_options;
+        self
+    }
+
+    /// Sets the ID of the application, which is the start of the `User-Agent`.
+    pub fn application_id<S: Into<String>>(mut self, application_id: S) -> Self {
+        self.application_id = Some(application_id.into());
+        self
+    }
+
+    /// Sets the name and the version of the generated crate in the `User-Agent`.
+    pub fn telemetry<S: Into<String>, V: Into<String>>(mut self, crate_name: S, crate_version: V) -> Self {
+        self.telemetry = Some((crate_name.into(), crate_version.into()));
+        self
+    }
+
+    /// Logs the value of a header, instead of redacting it.
+    pub fn logged_header<S: Into<String>>(mut self, header: S) -> Self {
+        self.logged_headers.push(header.into());
+        self
+    }
+
+    /// Adds a policy that each request goes through once, before it is retried.
+    pub fn per_call_policy(mut self, policy: Arc<dyn Policy>) -> Self {
+        self.per_call_policies.push(policy);
+        self
+    }
+
+    /// Adds a policy that each try of a request goes through, after it is authorized.
+    pub fn per_retry_policy(mut self, policy: Arc<dyn Policy>) -> Self {
+        self.per_retry_policies.push(policy);
+        self
+    }
+
+    pub fn build(self) -> OperationConfig {
+        let token_credential_resource = self.token_credential_resource.unwrap_or_default();
+        let (crate_name, crate_version) = self
+            .telemetry
+            .unwrap_or_else(|| ("autorust".to_owned(), env!("CARGO_PKG_VERSION").to_owned()));
+        let mut policies: Vec<Arc<dyn Policy>> = vec![
+            Arc::new(RequestIdPolicy),
+            Arc::new(TelemetryPolicy::new(self.application_id.as_deref(), &crate_name, &crate_version)),
+        ];
+        policies.extend(self.per_call_policies);
+        policies.push(Arc::new(RetryPolicy::new(self.retry_options)));
+        if let Some(token_credential) = &self.token_credential {
+            policies.push(Arc::new(BearerTokenPolicy::new(
+                token_credential.clone(),
+                token_credential_resource.clone(),
+            )));
+        }
+        policies.extend(self.per_retry_policies);
+        policies.push(Arc::new(LoggingPolicy::new(self.logged_headers)));
+        OperationConfig {
+            api_version: self.api_version.unwrap_or_default(),
+            pipeline: Pipeline::new(self.http_client, policies),
+            base_path: self.base_path.unwrap_or_default(),
+            token_credential: self.token_credential,
+            token_credential_resource,
+            polling_interval: self.polling_interval.unwrap_or(DEFAULT_POLLING_INTERVAL),
+        }
+    }
+}
+
+/// The configuration of the operations, which sends their requests through its pipeline.
+pub struct OperationConfig {
+    api_version: String,
+    pipeline: Pipeline,
+    base_path: String,
+    token_credential: Option<Arc<dyn TokenCredential>>,
+    token_credential_resource: String,
+    polling_interval: Duration,
+}
+
+impl OperationConfig {
+    pub fn api_version(&self) -> &str {
+        self.api_version.as_str()
+    }
+
+    pub fn http_client(&self) -> &dyn HttpClient {
+        self.pipeline.http_client()
+    }
+
+    pub fn pipeline(&self) -> &Pipeline {
+        &self.pipeline
+    }
+
+    pub fn base_path(&self) -> &str {
+        self.base_path.as_str()
+    }
+
+    pub fn token_credential(&self) -> Option<&dyn TokenCredential> {
+        self.token_credential.as_deref()
+    }
+
+    pub fn token_credential_resource(&self) -> &str {
+        self.token_credential_resource.as_str()
+    }
+
+    pub fn polling_interval(&self) -> Duration {
+        self.polling_interval
+    }
+
+    /// Sends a request through the pipeline.
+    pub async fn send(&self, request: Request) -> Result<Response> {
+        let response = self.pipeline.send(request).await?;
+        crate::trace::record_response(&response);
+        Ok(response)
+    }
+}
@@ -0,0 +1,38 @@
+use bytes::Bytes;
+use snafu::Snafu;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The errors of the HTTP clients and the token credentials.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub enum Error {
+    #[snafu(display("Could not parse URL {}: {}", url, source))]
+    ParseUrlError {
+        source: url::ParseError,
+        url: String,
+    },
+    BuildRequestError {
+        source: http::Error,
+    },
+    ExecuteRequestError {
+        source: BoxError,
+    },
+    GetTokenError {
+        source: BoxError,
+    },
+    SerializeError {
+        source: serde_json::Error,
+    },
+    DeserializeError {
+        source: serde_json::Error,
+        body: Bytes,
+    },
+    #[snafu(display("The long-running operation ended with status {}", status))]
+    LongRunningOperationError {
+        status: String,
+        body: Bytes,
+    },
+}
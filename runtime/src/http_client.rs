@@ -0,0 +1,16 @@
+use crate::{BoxError, Response};
+use bytes::Bytes;
+use std::{future::Future, pin::Pin};
+
+/// A future that is boxed, so that it can be returned by the methods of the traits.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Sends the HTTP requests of the operations.
+pub trait HttpClient: Send + Sync {
+    fn execute_request(&self, request: http::Request<Bytes>) -> BoxFuture<'_, Result<Response, BoxError>>;
+}
+
+/// Gets the bearer tokens that authorize the requests to a resource.
+pub trait TokenCredential: Send + Sync {
+    fn get_token<'a>(&'a self, resource: &'a str) -> BoxFuture<'a, Result<String, BoxError>>;
+}
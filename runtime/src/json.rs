@@ -0,0 +1,15 @@
+//! The JSON serialization of the request and response bodies.
+
+use crate::{error::*, Response};
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+use snafu::ResultExt;
+
+pub fn to_json<T: Serialize + ?Sized>(value: &T) -> Result<Bytes> {
+    Ok(Bytes::from(serde_json::to_vec(value).context(SerializeError)?))
+}
+
+pub fn from_json<T: DeserializeOwned>(response: &Response) -> Result<T> {
+    let body = response.body();
+    serde_json::from_slice(body).context(DeserializeError { body: body.clone() })
+}
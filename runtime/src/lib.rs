@@ -0,0 +1,42 @@
+//! The runtime of the crates generated by AutoRust, which the generated operations share,
+//! so that the generated crates only contain what is specific to their specifications.
+
+#[cfg(feature = "azure_core")]
+pub mod azure;
+pub mod blocking;
+mod config;
+mod error;
+mod http_client;
+pub mod json;
+pub mod lro;
+mod paging;
+mod pipeline;
+pub mod policies;
+mod request;
+#[cfg(feature = "reqwest")]
+mod reqwest_client;
+pub mod retry;
+mod sleep;
+pub mod trace;
+#[cfg(feature = "ureq")]
+mod ureq_client;
+
+pub use self::{
+    blocking::{block_on, BlockingPager},
+    config::{OperationConfig, OperationConfigBuilder},
+    error::{BoxError, Error, Result},
+    http_client::{BoxFuture, HttpClient, TokenCredential},
+    json::{from_json, to_json},
+    paging::Pager,
+    pipeline::{Next, Pipeline, Policy},
+    request::Request,
+    sleep::{sleep, Sleep},
+};
+
+#[cfg(feature = "reqwest")]
+pub use reqwest;
+#[cfg(feature = "ureq")]
+pub use ureq;
+
+/// The HTTP response to a request.
+pub type Response = http::Response<bytes::Bytes>;
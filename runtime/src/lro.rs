@@ -0,0 +1,92 @@
+//! Long-running operations, which are polled until they are done.
+//! The initial response links to the status of the operation with an `Azure-AsyncOperation` or `Location` header.
+
+use crate::{error::*, retry::get_retry_after, sleep, trace, OperationConfig, Request, Response};
+use http::{Method, StatusCode};
+use url::Url;
+
+const AZURE_ASYNC_OPERATION: &str = "azure-asyncoperation";
+
+/// Waits until a long-running operation is done, given its initial response, and gets its final response.
+/// The initial response is final when it does not say that the operation is still running.
+pub async fn wait_for_completion(operation_config: &OperationConfig, method: &Method, url: &Url, response: Response) -> Result<Response> {
+    if response.status() != StatusCode::CREATED && response.status() != StatusCode::ACCEPTED {
+        return Ok(response);
+    }
+    let async_operation = get_header(&response, AZURE_ASYNC_OPERATION);
+    let location = get_header(&response, http::header::LOCATION.as_str());
+    let mut polling_interval = get_retry_after(&response).unwrap_or_else(|| operation_config.polling_interval());
+    match (async_operation, location) {
+        (Some(async_operation), location) => {
+            let response = loop {
+                sleep(polling_interval).await;
+                let response = operation_config.send(Request::new(Method::GET, &async_operation)?).await?;
+                let status = get_status(response.body());
+                trace::poll_event(response.status(), status.as_deref());
+                if !response.status().is_success() {
+                    return Ok(response);
+                }
+                match status.as_deref() {
+                    Some(status) if status.eq_ignore_ascii_case("succeeded") => break response,
+                    Some(status) if is_failed(status) => {
+                        return LongRunningOperationError {
+                            status,
+                            body: response.body().clone(),
+                        }
+                        .fail()
+                    }
+                    _ => {}
+                }
+                polling_interval = get_retry_after(&response).unwrap_or_else(|| operation_config.polling_interval());
+            };
+            // the result of a put or a patch is the resource, and the result of a post may be at its location
+            if method == Method::PUT || method == Method::PATCH {
+                operation_config.send(Request::new(Method::GET, url.as_str())?).await
+            } else {
+                match location {
+                    Some(location) if method == Method::POST => operation_config.send(Request::new(Method::GET, &location)?).await,
+                    _ => Ok(response),
+                }
+            }
+        }
+        (None, Some(location)) => loop {
+            sleep(polling_interval).await;
+            let response = operation_config.send(Request::new(Method::GET, &location)?).await?;
+            trace::poll_event(response.status(), None);
+            if response.status() != StatusCode::ACCEPTED {
+                return Ok(response);
+            }
+            polling_interval = get_retry_after(&response).unwrap_or_else(|| operation_config.polling_interval());
+        },
+        (None, None) => Ok(response),
+    }
+}
+
+fn get_header(response: &Response, name: &str) -> Option<String> {
+    response.headers().get(name)?.to_str().ok().map(str::to_owned)
+}
+
+/// Gets the status of an operation from the body of an `Azure-AsyncOperation` response.
+fn get_status(body: &[u8]) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    value.get("status")?.as_str().map(str::to_owned)
+}
+
+fn is_failed(status: &str) -> bool {
+    ["failed", "canceled", "cancelled"]
+        .iter()
+        .any(|failed| status.eq_ignore_ascii_case(failed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_status() {
+        assert_eq!(get_status(br#"{"id":"1","status":"InProgress"}"#).as_deref(), Some("InProgress"));
+        assert_eq!(get_status(b"not json"), None);
+        assert!(is_failed("Canceled"));
+        assert!(!is_failed("Succeeded"));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{trace, Error, OperationConfig, Request, Response};
+use http::Method;
+
+/// The pages of a pageable operation, where each page links to the next one.
+pub struct Pager<'a, T, E> {
+    operation_config: &'a OperationConfig,
+    next_request: Option<Result<Request, E>>,
+    handle_response: fn(Response) -> Result<T, E>,
+    next_link: fn(&T) -> Option<String>,
+    map_error: fn(Error) -> E,
+    operation: Option<&'static trace::Operation>,
+}
+
+impl<'a, T, E> Pager<'a, T, E> {
+    pub fn new(
+        operation_config: &'a OperationConfig,
+        request: Result<Request, E>,
+        handle_response: fn(Response) -> Result<T, E>,
+        next_link: fn(&T) -> Option<String>,
+        map_error: fn(Error) -> E,
+    ) -> Self {
+        Self {
+            operation_config,
+            next_request: Some(request),
+            handle_response,
+            next_link,
+            map_error,
+            operation: None,
+        }
+    }
+
+    /// Gets each page in a span of the operation.
+    pub fn with_operation(mut self, operation: &'static trace::Operation) -> Self {
+        self.operation = Some(operation);
+        self
+    }
+
+    /// Gets the next page, or `None` after the last one. There are no more pages after an error.
+    pub async fn next_page(&mut self) -> Option<Result<T, E>> {
+        match self.operation {
+            Some(operation) => trace::instrument(operation, self.get_next_page()).await,
+            None => self.get_next_page().await,
+        }
+    }
+
+    async fn get_next_page(&mut self) -> Option<Result<T, E>> {
+        let request = match self.next_request.take()? {
+            Ok(request) => request,
+            Err(err) => return Some(Err(err)),
+        };
+        let response = match self.operation_config.send(request).await {
+            Ok(response) => response,
+            Err(err) => return Some(Err((self.map_error)(err))),
+        };
+        let page = match (self.handle_response)(response) {
+            Ok(page) => page,
+            Err(err) => return Some(Err(err)),
+        };
+        if let Some(next_link) = (self.next_link)(&page).filter(|next_link| !next_link.is_empty()) {
+            self.next_request = Some(Request::new(Method::GET, &next_link).map_err(self.map_error));
+        }
+        Some(Ok(page))
+    }
+
+    /// Gets all of the pages, stopping at the first error.
+    pub async fn collect(mut self) -> Result<Vec<T>, E> {
+        let mut pages = Vec::new();
+        while let Some(page) = self.next_page().await {
+            pages.push(page?);
+        }
+        Ok(pages)
+    }
+}
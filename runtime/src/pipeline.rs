@@ -0,0 +1,163 @@
+use crate::{error::*, BoxFuture, HttpClient, Request, Response};
+use snafu::ResultExt;
+use std::sync::Arc;
+
+/// A policy of the pipeline, which may change a request before it sends it with the next policies,
+/// and may change the response, or send the request again.
+pub trait Policy: Send + Sync {
+    fn send<'a>(&'a self, request: Request, next: Next<'a>) -> BoxFuture<'a, Result<Response>>;
+}
+
+/// The policies after a policy, which end with the HTTP client.
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    policies: &'a [Arc<dyn Policy>],
+    http_client: &'a dyn HttpClient,
+}
+
+impl<'a> Next<'a> {
+    pub fn send(self, request: Request) -> BoxFuture<'a, Result<Response>> {
+        match self.policies.split_first() {
+            Some((policy, policies)) => policy.send(
+                request,
+                Next {
+                    policies,
+                    http_client: self.http_client,
+                },
+            ),
+            None => {
+                let http_client = self.http_client;
+                Box::pin(async move {
+                    let request = request.into_http()?;
+                    http_client.execute_request(request).await.context(ExecuteRequestError)
+                })
+            }
+        }
+    }
+}
+
+/// The policies that each request goes through before it is sent by the HTTP client.
+pub struct Pipeline {
+    policies: Vec<Arc<dyn Policy>>,
+    http_client: Arc<dyn HttpClient>,
+}
+
+impl Pipeline {
+    pub fn new(http_client: Arc<dyn HttpClient>, policies: Vec<Arc<dyn Policy>>) -> Self {
+        Self { policies, http_client }
+    }
+
+    pub fn policies(&self) -> &[Arc<dyn Policy>] {
+        &self.policies
+    }
+
+    pub fn http_client(&self) -> &dyn HttpClient {
+        self.http_client.as_ref()
+    }
+
+    pub fn send(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+        Next {
+            policies: &self.policies,
+            http_client: self.http_client.as_ref(),
+        }
+        .send(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block_on, retry::RetryOptions, BoxError, OperationConfigBuilder};
+    use bytes::Bytes;
+    use std::{sync::Mutex, time::Duration};
+
+    struct MockClient {
+        statuses: Mutex<Vec<u16>>,
+        requests: Mutex<Vec<http::Request<Bytes>>>,
+    }
+
+    impl HttpClient for MockClient {
+        fn execute_request(&self, request: http::Request<Bytes>) -> BoxFuture<'_, std::result::Result<Response, BoxError>> {
+            self.requests.lock().unwrap().push(request);
+            let status = self.statuses.lock().unwrap().remove(0);
+            Box::pin(async move { Ok(http::Response::builder().status(status).body(Bytes::new())?) })
+        }
+    }
+
+    struct HeaderPolicy;
+
+    impl Policy for HeaderPolicy {
+        fn send<'a>(&'a self, mut request: Request, next: Next<'a>) -> BoxFuture<'a, Result<Response>> {
+            request.header("x-custom", "1");
+            next.send(request)
+        }
+    }
+
+    #[test]
+    fn test_retry() -> Result<()> {
+        let client = Arc::new(MockClient {
+            statuses: Mutex::new(vec![503, 429, 200]),
+            requests: Mutex::new(Vec::new()),
+        });
+        let config = OperationConfigBuilder::new(client.clone())
+            .retry_options(RetryOptions {
+                initial_delay: Duration::from_millis(1),
+                ..Default::default()
+            })
+            .telemetry("azure_mgmt_test", "0.1.0")
+            .per_call_policy(Arc::new(HeaderPolicy))
+            .build();
+        let response = block_on(config.send(Request::new(http::Method::GET, "https://example.com/widgets")?))?;
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        let requests = client.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        let request_id = &requests[0].headers()["x-ms-client-request-id"];
+        for request in requests.iter() {
+            assert_eq!(&request.headers()["x-ms-client-request-id"], request_id);
+            assert_eq!(request.headers().get_all("x-custom").iter().count(), 1);
+            assert!(request.headers()["user-agent"]
+                .to_str()
+                .unwrap()
+                .starts_with("azure_mgmt_test/0.1.0 "));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_non_idempotent() -> Result<()> {
+        let send = |statuses: Vec<u16>, retry_non_idempotent: bool| -> Result<(http::StatusCode, usize)> {
+            let client = Arc::new(MockClient {
+                statuses: Mutex::new(statuses),
+                requests: Mutex::new(Vec::new()),
+            });
+            let config = OperationConfigBuilder::new(client.clone())
+                .retry_options(RetryOptions {
+                    initial_delay: Duration::from_millis(1),
+                    retry_non_idempotent,
+                    ..Default::default()
+                })
+                .build();
+            let response = block_on(config.send(Request::new(http::Method::POST, "https://example.com/widgets")?))?;
+            let count = client.requests.lock().unwrap().len();
+            Ok((response.status(), count))
+        };
+        assert_eq!(send(vec![503, 200], false)?, (http::StatusCode::SERVICE_UNAVAILABLE, 1));
+        assert_eq!(send(vec![429, 200], false)?, (http::StatusCode::OK, 2));
+        assert_eq!(send(vec![503, 200], true)?, (http::StatusCode::OK, 2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_retry() -> Result<()> {
+        let client = Arc::new(MockClient {
+            statuses: Mutex::new(vec![404, 200]),
+            requests: Mutex::new(Vec::new()),
+        });
+        let config = OperationConfigBuilder::new(client.clone()).build();
+        let response = block_on(config.send(Request::new(http::Method::GET, "https://example.com/widgets")?))?;
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+        assert_eq!(client.requests.lock().unwrap().len(), 1);
+        Ok(())
+    }
+}
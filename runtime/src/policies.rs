@@ -0,0 +1,225 @@
+//! The policies that are built into the pipeline of an `OperationConfig`.
+
+use crate::{error::*, BoxFuture, Next, Policy, Request, Response, TokenCredential};
+use snafu::ResultExt;
+use std::{
+    collections::{hash_map::RandomState, HashSet},
+    hash::{BuildHasher, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Instant, SystemTime},
+};
+
+pub const CLIENT_REQUEST_ID: &str = "x-ms-client-request-id";
+
+/// Identifies the client in the `User-Agent` header.
+pub struct TelemetryPolicy {
+    user_agent: String,
+}
+
+impl TelemetryPolicy {
+    pub fn new(application_id: Option<&str>, crate_name: &str, crate_version: &str) -> Self {
+        let mut user_agent = format!(
+            "{}/{} autorust_runtime/{} ({}; {})",
+            crate_name,
+            crate_version,
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        );
+        if let Some(application_id) = application_id {
+            user_agent = format!("{} {}", application_id, user_agent);
+        }
+        Self { user_agent }
+    }
+}
+
+impl Policy for TelemetryPolicy {
+    fn send<'a>(&'a self, mut request: Request, next: Next<'a>) -> BoxFuture<'a, Result<Response>> {
+        if !request.has_header(http::header::USER_AGENT.as_str()) {
+            request.header(http::header::USER_AGENT.as_str(), &self.user_agent);
+        }
+        next.send(request)
+    }
+}
+
+/// Sets a unique `x-ms-client-request-id`, which is the same for all the retries of a request,
+/// so that the request can be found in the logs of the service.
+pub struct RequestIdPolicy;
+
+impl Policy for RequestIdPolicy {
+    fn send<'a>(&'a self, mut request: Request, next: Next<'a>) -> BoxFuture<'a, Result<Response>> {
+        if !request.has_header(CLIENT_REQUEST_ID) {
+            request.header(CLIENT_REQUEST_ID, &new_request_id());
+        }
+        next.send(request)
+    }
+}
+
+/// Creates a random ID in the format of a version 4 UUID.
+fn new_request_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let random = |salt: u64| {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        hasher.write_u64(salt);
+        if let Ok(time) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+            hasher.write_u128(time.as_nanos());
+        }
+        hasher.finish()
+    };
+    let high = random(0);
+    let low = random(1);
+    format!(
+        "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0x0fff,
+        (low >> 48) & 0x3fff | 0x8000,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+/// Authorizes each request, including each retry, with a bearer token from a token credential.
+pub struct BearerTokenPolicy {
+    token_credential: Arc<dyn TokenCredential>,
+    resource: String,
+}
+
+impl BearerTokenPolicy {
+    pub fn new(token_credential: Arc<dyn TokenCredential>, resource: String) -> Self {
+        Self {
+            token_credential,
+            resource,
+        }
+    }
+}
+
+impl Policy for BearerTokenPolicy {
+    fn send<'a>(&'a self, mut request: Request, next: Next<'a>) -> BoxFuture<'a, Result<Response>> {
+        Box::pin(async move {
+            let token = self.token_credential.get_token(&self.resource).await.context(GetTokenError)?;
+            request.set_header(http::header::AUTHORIZATION.as_str(), &format!("Bearer {}", token));
+            next.send(request).await
+        })
+    }
+}
+
+/// The headers that are logged with their values. The values of the other headers are redacted.
+const LOGGED_HEADERS: &[&str] = &[
+    "accept",
+    "cache-control",
+    "connection",
+    "content-length",
+    "content-type",
+    "date",
+    "etag",
+    "expires",
+    "if-match",
+    "if-modified-since",
+    "if-none-match",
+    "if-unmodified-since",
+    "last-modified",
+    "pragma",
+    "retry-after",
+    "server",
+    "transfer-encoding",
+    "user-agent",
+    "x-ms-client-request-id",
+    "x-ms-correlation-request-id",
+    "x-ms-request-id",
+    "x-ms-retry-after-ms",
+    "retry-after-ms",
+];
+
+const REDACTED: &str = "REDACTED";
+
+/// Logs each request that is sent, and its response, at the debug level, redacting the values of the headers
+/// that may be secret, such as `Authorization`.
+pub struct LoggingPolicy {
+    logged_headers: HashSet<String>,
+}
+
+impl LoggingPolicy {
+    /// Creates the policy, where the values of some headers are logged along with those that are always logged.
+    pub fn new<I: IntoIterator<Item = String>>(logged_headers: I) -> Self {
+        let mut all_logged_headers: HashSet<String> = LOGGED_HEADERS.iter().map(|header| (*header).to_owned()).collect();
+        all_logged_headers.extend(logged_headers.into_iter().map(|header| header.to_ascii_lowercase()));
+        Self {
+            logged_headers: all_logged_headers,
+        }
+    }
+
+    fn format_header(&self, name: &str, value: &str) -> String {
+        if self.logged_headers.contains(&name.to_ascii_lowercase()) {
+            format!("{}: {}", name, value)
+        } else {
+            format!("{}: {}", name, REDACTED)
+        }
+    }
+
+    fn format_request(&self, request: &Request) -> String {
+        let headers: Vec<_> = request.headers().map(|(name, value)| self.format_header(name, value)).collect();
+        format!("{} {} {:?}", request.method(), request.url(), headers)
+    }
+
+    fn format_response(&self, response: &Response) -> String {
+        let headers: Vec<_> = response
+            .headers()
+            .iter()
+            .map(|(name, value)| self.format_header(name.as_str(), value.to_str().unwrap_or_default()))
+            .collect();
+        format!("{} {:?}", response.status(), headers)
+    }
+}
+
+impl Policy for LoggingPolicy {
+    fn send<'a>(&'a self, request: Request, next: Next<'a>) -> BoxFuture<'a, Result<Response>> {
+        Box::pin(async move {
+            if log::log_enabled!(log::Level::Debug) {
+                log::debug!("request {}", self.format_request(&request));
+            }
+            let url = request.url().clone();
+            let start = Instant::now();
+            let result = next.send(request).await;
+            match &result {
+                Ok(response) if log::log_enabled!(log::Level::Debug) => {
+                    log::debug!("response from {} in {:?} {}", url, start.elapsed(), self.format_response(response))
+                }
+                Err(err) => log::debug!("request to {} failed in {:?}: {}", url, start.elapsed(), err),
+                _ => {}
+            }
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_request_id() {
+        let id = new_request_id();
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+        assert!(matches!(&id[19..20], "8" | "9" | "a" | "b"));
+        assert_ne!(id, new_request_id());
+    }
+
+    #[test]
+    fn test_redact_headers() -> Result<()> {
+        let policy = LoggingPolicy::new(vec!["X-Custom".to_owned()]);
+        let mut request = Request::new(http::Method::GET, "https://example.com/widgets")?;
+        request.header("Authorization", "Bearer secret");
+        request.header("x-custom", "shown");
+        request.header("x-ms-client-request-id", "1");
+        assert_eq!(
+            policy.format_request(&request),
+            r#"GET https://example.com/widgets ["Authorization: REDACTED", "x-custom: shown", "x-ms-client-request-id: 1"]"#
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{error::*, json::to_json};
+use bytes::Bytes;
+use http::Method;
+use serde::Serialize;
+use snafu::ResultExt;
+use url::Url;
+
+/// An HTTP request of an operation, which is built by the generated code and sent with `OperationConfig::send`.
+#[derive(Clone, Debug)]
+pub struct Request {
+    method: Method,
+    url: Url,
+    headers: Vec<(String, String)>,
+    form: Vec<(String, String)>,
+    body: Bytes,
+}
+
+impl Request {
+    pub fn new(method: Method, url: &str) -> Result<Self> {
+        Ok(Self {
+            method,
+            url: Url::parse(url).context(ParseUrlError { url })?,
+            headers: Vec::new(),
+            form: Vec::new(),
+            body: Bytes::new(),
+        })
+    }
+
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn headers(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn has_header(&self, name: &str) -> bool {
+        self.headers.iter().any(|(header, _)| header.eq_ignore_ascii_case(name))
+    }
+
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    pub fn query(&mut self, name: &str, value: &str) {
+        self.url.query_pairs_mut().append_pair(name, value);
+    }
+
+    /// Adds a header. An invalid name or value fails when the request is sent.
+    pub fn header(&mut self, name: &str, value: &str) {
+        self.headers.push((name.to_owned(), value.to_owned()));
+    }
+
+    /// Sets a header, replacing the values that it had.
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        self.headers.retain(|(header, _)| !header.eq_ignore_ascii_case(name));
+        self.header(name, value);
+    }
+
+    pub fn json<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.body = to_json(value)?;
+        self.set_header(http::header::CONTENT_TYPE.as_str(), "application/json");
+        Ok(())
+    }
+
+    /// Adds a field to the form, which is sent URL encoded as the body.
+    pub fn form(&mut self, name: &str, value: &str) {
+        self.form.push((name.to_owned(), value.to_owned()));
+    }
+
+    pub fn into_http(self) -> Result<http::Request<Bytes>> {
+        let mut body = self.body;
+        let mut builder = http::Request::builder().method(self.method.clone()).uri(self.url.as_str());
+        for (name, value) in &self.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        if !self.form.is_empty() {
+            let form = url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(&self.form)
+                .finish();
+            body = Bytes::from(form);
+            builder = builder.header(http::header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+        }
+        // a post without a body still needs its length
+        if body.is_empty() && self.method == Method::POST {
+            builder = builder.header(http::header::CONTENT_LENGTH, 0);
+        }
+        builder.body(body).context(BuildRequestError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_http() -> Result<()> {
+        let mut request = Request::new(Method::POST, "https://example.com/widgets/a%20b")?;
+        request.query("api-version", "2020-01-01");
+        request.query("$filter", "name eq 'a'");
+        request.header("x-ms-fixed", "always");
+        let request = request.into_http()?;
+        assert_eq!(
+            request.uri(),
+            "https://example.com/widgets/a%20b?api-version=2020-01-01&%24filter=name+eq+%27a%27"
+        );
+        assert_eq!(request.headers()["x-ms-fixed"], "always");
+        assert_eq!(request.headers()[http::header::CONTENT_LENGTH], "0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_json() -> Result<()> {
+        let mut request = Request::new(Method::PUT, "https://example.com/widgets/a")?;
+        request.json(&serde_json::json!({ "name": "a" }))?;
+        let request = request.into_http()?;
+        assert_eq!(request.headers()[http::header::CONTENT_TYPE], "application/json");
+        assert_eq!(request.body().as_ref(), br#"{"name":"a"}"#);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_url() {
+        assert!(matches!(Request::new(Method::GET, "no url"), Err(Error::ParseUrlError { .. })));
+    }
+}
@@ -0,0 +1,24 @@
+//! A `reqwest::Client` as the HTTP client, for the crates that do not use `azure_core`.
+
+use crate::{BoxError, BoxFuture, Response};
+use bytes::Bytes;
+
+impl crate::HttpClient for reqwest::Client {
+    fn execute_request(&self, request: http::Request<Bytes>) -> BoxFuture<'_, Result<Response, BoxError>> {
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let rsp = self
+                .request(parts.method, &parts.uri.to_string())
+                .headers(parts.headers)
+                .body(body)
+                .send()
+                .await?;
+            let status = rsp.status();
+            let headers = rsp.headers().clone();
+            let body = rsp.bytes().await?;
+            let mut response = http::Response::builder().status(status).body(body)?;
+            *response.headers_mut() = headers;
+            Ok(response)
+        })
+    }
+}
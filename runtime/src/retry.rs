@@ -0,0 +1,147 @@
+use crate::{error::*, sleep, trace, BoxFuture, Next, Policy, Request, Response};
+use http::{Method, StatusCode};
+use std::time::Duration;
+
+/// How many times a request is retried, and how long to wait in between, when the service does not say.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryOptions {
+    pub max_retries: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Whether the requests that are not idempotent, such as `POST` and `PATCH`, are also retried
+    /// after failing to be sent or after a server error, when the service may already have acted on them.
+    /// They are always retried when throttled.
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryOptions {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_delay: Duration::from_millis(800),
+            max_delay: Duration::from_secs(60),
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryOptions {
+    /// The exponential backoff before a retry, which doubles after each one.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry);
+        self.initial_delay.checked_mul(factor).unwrap_or(self.max_delay).min(self.max_delay)
+    }
+}
+
+/// Retries the requests that fail to be sent, or are throttled, or fail with a transient server error.
+/// Only the idempotent requests are retried after a failure, unless the options say otherwise.
+pub struct RetryPolicy {
+    options: RetryOptions,
+}
+
+impl RetryPolicy {
+    pub fn new(options: RetryOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl Policy for RetryPolicy {
+    fn send<'a>(&'a self, request: Request, next: Next<'a>) -> BoxFuture<'a, Result<Response>> {
+        Box::pin(async move {
+            let retry_failures = self.options.retry_non_idempotent || is_idempotent(request.method());
+            let mut retry = 0;
+            loop {
+                let result = next.send(request.clone()).await;
+                let retry_after = match &result {
+                    Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => get_retry_after(response),
+                    Ok(response) if retry_failures && is_retryable(response.status()) => get_retry_after(response),
+                    Err(Error::ExecuteRequestError { .. }) if retry_failures => None,
+                    _ => return result,
+                };
+                if retry >= self.options.max_retries {
+                    return result;
+                }
+                let delay = retry_after.unwrap_or_else(|| self.options.delay(retry));
+                retry += 1;
+                log::debug!("retry {} of {} {} in {:?}", retry, request.method(), request.url(), delay);
+                trace::retry_event(
+                    request.method(),
+                    retry,
+                    result.as_ref().ok().map(|response| response.status()),
+                    delay,
+                );
+                sleep(delay).await;
+            }
+        })
+    }
+}
+
+/// Whether sending a request more than once has the same effect as sending it once.
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS | Method::TRACE
+    )
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::REQUEST_TIMEOUT
+            | StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Gets how long to wait before retrying or polling from the `retry-after-ms`, `x-ms-retry-after-ms`
+/// or `Retry-After` header. The `Retry-After` header may only be in seconds.
+pub fn get_retry_after(response: &Response) -> Option<Duration> {
+    let header = |name: &str| response.headers().get(name)?.to_str().ok()?.trim().parse::<u64>().ok();
+    header("retry-after-ms")
+        .or_else(|| header("x-ms-retry-after-ms"))
+        .map(Duration::from_millis)
+        .or_else(|| header(http::header::RETRY_AFTER.as_str()).map(Duration::from_secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    #[test]
+    fn test_delay() {
+        let options = RetryOptions::default();
+        assert_eq!(options.delay(0), Duration::from_millis(800));
+        assert_eq!(options.delay(2), Duration::from_millis(3200));
+        assert_eq!(options.delay(40), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_is_idempotent() {
+        assert!(is_idempotent(&Method::GET));
+        assert!(is_idempotent(&Method::PUT));
+        assert!(is_idempotent(&Method::DELETE));
+        assert!(!is_idempotent(&Method::POST));
+        assert!(!is_idempotent(&Method::PATCH));
+    }
+
+    #[test]
+    fn test_get_retry_after() {
+        let response = http::Response::builder().header("Retry-After", "5").body(Bytes::new()).unwrap();
+        assert_eq!(get_retry_after(&response), Some(Duration::from_secs(5)));
+        let response = http::Response::builder()
+            .header("Retry-After", "5")
+            .header("x-ms-retry-after-ms", "200")
+            .body(Bytes::new())
+            .unwrap();
+        assert_eq!(get_retry_after(&response), Some(Duration::from_millis(200)));
+        let response = http::Response::builder()
+            .header("Retry-After", "Wed, 21 Oct 2015 07:28:00 GMT")
+            .body(Bytes::new())
+            .unwrap();
+        assert_eq!(get_retry_after(&response), None);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::blocking;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex, OnceLock},
+    task::{Context, Poll, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Waits for a duration, such as between retries or polls. It uses the timer of the executor that polls it:
+/// the one of tokio in a tokio runtime, with the `tokio` feature, or the one of `block_on`.
+/// Other executors share a single timer thread.
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        deadline: Instant::now() + duration,
+        timer: None,
+    }
+}
+
+pub struct Sleep {
+    deadline: Instant,
+    timer: Option<Timer>,
+}
+
+/// The waker of the task that last polled a sleep, which the shared timer takes to wake it.
+type SharedWaker = Arc<Mutex<Option<Waker>>>;
+
+enum Timer {
+    #[cfg(feature = "tokio")]
+    Tokio(Pin<Box<tokio::time::Sleep>>),
+    BlockOn,
+    Shared(SharedWaker),
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let deadline = self.deadline;
+        match &mut self.timer {
+            #[cfg(feature = "tokio")]
+            Some(Timer::Tokio(sleep)) => return sleep.as_mut().poll(cx),
+            Some(Timer::BlockOn) => {
+                if Instant::now() >= deadline {
+                    return Poll::Ready(());
+                }
+                blocking::wake_at(deadline);
+                return Poll::Pending;
+            }
+            Some(Timer::Shared(waker)) => {
+                if Instant::now() >= deadline {
+                    return Poll::Ready(());
+                }
+                *waker.lock().unwrap() = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+            None => {}
+        }
+        if Instant::now() >= deadline {
+            return Poll::Ready(());
+        }
+        #[cfg(feature = "tokio")]
+        {
+            if tokio::runtime::Handle::try_current().is_ok() {
+                let mut sleep = Box::pin(tokio::time::sleep_until(tokio::time::Instant::from_std(deadline)));
+                let poll = sleep.as_mut().poll(cx);
+                self.timer = Some(Timer::Tokio(sleep));
+                return poll;
+            }
+        }
+        if blocking::wake_at(deadline) {
+            self.timer = Some(Timer::BlockOn);
+            return Poll::Pending;
+        }
+        let waker = Arc::new(Mutex::new(Some(cx.waker().clone())));
+        shared_timer().add(deadline, waker.clone());
+        self.timer = Some(Timer::Shared(waker));
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(Timer::Shared(waker)) = &self.timer {
+            waker.lock().unwrap().take();
+        }
+    }
+}
+
+/// The timer thread for the executors without a timer that the runtime knows of.
+/// It wakes the tasks of the sleeps that have passed their deadlines.
+struct SharedTimer {
+    sleeps: Mutex<Vec<(Instant, SharedWaker)>>,
+    added: Condvar,
+}
+
+fn shared_timer() -> &'static SharedTimer {
+    static TIMER: OnceLock<SharedTimer> = OnceLock::new();
+    let mut started = false;
+    let timer = TIMER.get_or_init(|| {
+        started = true;
+        SharedTimer {
+            sleeps: Mutex::new(Vec::new()),
+            added: Condvar::new(),
+        }
+    });
+    if started {
+        thread::Builder::new()
+            .name("autorust_runtime timer".to_owned())
+            .spawn(move || timer.run())
+            .expect("spawn the timer thread");
+    }
+    timer
+}
+
+impl SharedTimer {
+    fn add(&self, deadline: Instant, waker: SharedWaker) {
+        self.sleeps.lock().unwrap().push((deadline, waker));
+        self.added.notify_one();
+    }
+
+    fn run(&self) {
+        let mut sleeps = self.sleeps.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            sleeps.retain(|(deadline, waker)| {
+                if *deadline > now {
+                    return true;
+                }
+                if let Some(waker) = waker.lock().unwrap().take() {
+                    waker.wake();
+                }
+                false
+            });
+            sleeps = match sleeps.iter().map(|(deadline, _)| *deadline).min() {
+                Some(deadline) => self.added.wait_timeout(sleeps, deadline - now).unwrap().0,
+                None => self.added.wait(sleeps).unwrap(),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::mpsc, task::Wake};
+
+    struct ChannelWaker(Mutex<mpsc::Sender<()>>);
+
+    impl Wake for ChannelWaker {
+        fn wake(self: Arc<Self>) {
+            let _ = self.0.lock().unwrap().send(());
+        }
+    }
+
+    #[test]
+    fn test_shared_timer() {
+        let (sender, receiver) = mpsc::channel();
+        let waker = Waker::from(Arc::new(ChannelWaker(Mutex::new(sender))));
+        let mut cx = Context::from_waker(&waker);
+        let mut long = sleep(Duration::from_secs(60));
+        let mut short = sleep(Duration::from_millis(10));
+        assert_eq!(Pin::new(&mut long).poll(&mut cx), Poll::Pending);
+        assert_eq!(Pin::new(&mut short).poll(&mut cx), Poll::Pending);
+        assert!(matches!(short.timer, Some(Timer::Shared(_))));
+        receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(Pin::new(&mut short).poll(&mut cx), Poll::Ready(()));
+        assert_eq!(Pin::new(&mut long).poll(&mut cx), Poll::Pending);
+    }
+}
@@ -0,0 +1,129 @@
+//! Tracing the operations with the `tracing` crate, when the `tracing` feature is enabled.
+//! Each operation has a span, and the retries and the polls of long-running operations are events within it.
+//! Without the feature, the operations are not traced and there is nothing to pay for it.
+
+use crate::Response;
+use http::{Method, StatusCode};
+use std::{future::Future, time::Duration};
+
+/// Identifies an operation in its span.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Operation {
+    /// The `operationId` from the specification.
+    pub id: &'static str,
+    pub method: &'static str,
+    /// The path template from the specification, such as `/subscriptions/{subscriptionId}`,
+    /// so that the values of the parameters are not in the span.
+    pub path: &'static str,
+}
+
+/// Runs an operation in its span, recording how long it took.
+/// The status code and the request IDs of its last response are also recorded in the span.
+#[cfg(feature = "tracing")]
+pub async fn instrument<F: Future>(operation: &Operation, future: F) -> F::Output {
+    use tracing::Instrument;
+    let span = create_span(operation);
+    let start = std::time::Instant::now();
+    let output = future.instrument(span.clone()).await;
+    span.record("duration_ms", start.elapsed().as_millis() as u64);
+    output
+}
+
+/// Runs an operation, which is not traced without the `tracing` feature.
+#[cfg(not(feature = "tracing"))]
+pub async fn instrument<F: Future>(_operation: &Operation, future: F) -> F::Output {
+    future.await
+}
+
+/// Runs a blocking operation in its span, recording how long it took.
+#[cfg(feature = "tracing")]
+pub fn instrument_blocking<T>(operation: &Operation, f: impl FnOnce() -> T) -> T {
+    let span = create_span(operation);
+    let start = std::time::Instant::now();
+    let output = span.in_scope(f);
+    span.record("duration_ms", start.elapsed().as_millis() as u64);
+    output
+}
+
+/// Runs a blocking operation, which is not traced without the `tracing` feature.
+#[cfg(not(feature = "tracing"))]
+pub fn instrument_blocking<T>(_operation: &Operation, f: impl FnOnce() -> T) -> T {
+    f()
+}
+
+#[cfg(feature = "tracing")]
+fn create_span(operation: &Operation) -> tracing::Span {
+    use tracing::field::Empty;
+    tracing::info_span!(
+        "operation",
+        operation_id = operation.id,
+        http.method = operation.method,
+        http.url = operation.path,
+        http.status_code = Empty,
+        duration_ms = Empty,
+        request_id = Empty,
+        correlation_request_id = Empty,
+    )
+}
+
+/// Records the status code and the `x-ms-request-id` and `x-ms-correlation-request-id` of a response in the current span.
+pub(crate) fn record_response(response: &Response) {
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::Span::current();
+        span.record("http.status_code", response.status().as_u16());
+        if let Some(request_id) = get_header(response, "x-ms-request-id") {
+            span.record("request_id", request_id);
+        }
+        if let Some(correlation_request_id) = get_header(response, "x-ms-correlation-request-id") {
+            span.record("correlation_request_id", correlation_request_id);
+        }
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = response;
+}
+
+#[cfg(feature = "tracing")]
+fn get_header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+    response.headers().get(name)?.to_str().ok()
+}
+
+/// An event for a retry, after a response with a status code or after failing to send the request.
+pub(crate) fn retry_event(method: &Method, retry: u32, status: Option<StatusCode>, delay: Duration) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        http.method = method.as_str(),
+        retry,
+        http.status_code = status.map(|status| status.as_u16()),
+        delay_ms = delay.as_millis() as u64,
+        "retrying request"
+    );
+    #[cfg(not(feature = "tracing"))]
+    let _ = (method, retry, status, delay);
+}
+
+/// An event for a poll of a long-running operation, with its status when the service says.
+pub(crate) fn poll_event(status_code: StatusCode, status: Option<&str>) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(http.status_code = status_code.as_u16(), status, "polled long-running operation");
+    #[cfg(not(feature = "tracing"))]
+    let _ = (status_code, status);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_on;
+
+    const OPERATION: Operation = Operation {
+        id: "Widgets_Get",
+        method: "GET",
+        path: "/widgets/{widgetName}",
+    };
+
+    #[test]
+    fn test_instrument() {
+        assert_eq!(block_on(instrument(&OPERATION, async { 1 })), 1);
+        assert_eq!(instrument_blocking(&OPERATION, || 2), 2);
+    }
+}
@@ -0,0 +1,33 @@
+//! A `ureq::Agent` as a synchronous HTTP client, for the blocking operations.
+
+use crate::{BoxError, BoxFuture, Response};
+use bytes::Bytes;
+use std::io::Read;
+
+impl crate::HttpClient for ureq::Agent {
+    /// Sends the request when the future is first polled, blocking the thread until the response.
+    fn execute_request(&self, request: http::Request<Bytes>) -> BoxFuture<'_, Result<Response, BoxError>> {
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let mut req = self.request(parts.method.as_str(), &parts.uri.to_string());
+            for (name, value) in &parts.headers {
+                req = req.set(name.as_str(), value.to_str()?);
+            }
+            let rsp = match req.send_bytes(&body) {
+                Ok(rsp) => rsp,
+                // the responses with error status codes are handled by the operations
+                Err(ureq::Error::Status(_, rsp)) => rsp,
+                Err(err) => return Err(err.into()),
+            };
+            let mut response = http::Response::builder().status(rsp.status());
+            for name in rsp.headers_names() {
+                for value in rsp.all(&name) {
+                    response = response.header(name.as_str(), value);
+                }
+            }
+            let mut body = Vec::new();
+            rsp.into_reader().read_to_end(&mut body)?;
+            Ok(response.body(Bytes::from(body))?)
+        })
+    }
+}